@@ -0,0 +1,49 @@
+// Times `stat_value` on flat records, which take the fast path, against the same
+// fields one level down, which take the general path. Run with `cargo bench`.
+use std::hint::black_box;
+use std::time::Instant;
+
+use jsonstat::JsonStat;
+use serde_json::{json, Value};
+
+const RECORDS: usize = 100_000;
+
+fn records(nested: bool) -> Vec<Value> {
+    (0..RECORDS)
+        .map(|i| {
+            let fields = json!({
+                "id": i,
+                "name": format!("user{}", i % 1000),
+                "score": i as f64 / 7.0,
+                "active": i % 3 == 0,
+                "tag": null,
+            });
+            if nested {
+                json!({ "data": fields })
+            } else {
+                fields
+            }
+        })
+        .collect()
+}
+
+fn bench(name: &str, records: &[Value]) {
+    let start = Instant::now();
+    let mut stat = JsonStat::new();
+    for record in records {
+        stat.stat_value(black_box(record));
+    }
+    let elapsed = start.elapsed();
+    black_box(&stat);
+    println!(
+        "{}: {:?} ({:.0} records/s)",
+        name,
+        elapsed,
+        records.len() as f64 / elapsed.as_secs_f64()
+    );
+}
+
+fn main() {
+    bench("flat", &records(false));
+    bench("nested", &records(true));
+}
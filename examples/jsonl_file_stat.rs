@@ -1,8 +1,6 @@
 use std::env;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
 
-use jsonstat::JsonStat;
+use jsonstat::{JsonStat, JsonStatConfig};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -11,12 +9,7 @@ fn main() {
         return;
     }
     let file = args.get(1).unwrap();
-    let f = File::open(file).expect("file open error");
-    let mut stat = JsonStat::new();
-    for line in BufReader::new(f).lines() {
-        let data = line.expect("file read error");
-        stat.stat_str(&data);
-    }
+    let stat = JsonStat::from_jsonl_file(file, JsonStatConfig::default()).expect("file read error");
     println!("{}", stat.to_json_str(false));
     println!("{}", stat.to_json_str(true));
 }
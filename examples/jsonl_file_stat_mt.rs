@@ -1,6 +1,6 @@
 use std::env;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::BufReader;
 use std::sync::mpsc::channel;
 
 use jsonstat::JsonStat;
@@ -18,10 +18,8 @@ fn main() {
         pool.execute(move || {
             let f = File::open(file).expect("file open error");
             let mut stat = JsonStat::new();
-            for line in BufReader::new(f).lines() {
-                let data = line.expect("file read error");
-                stat.stat_str(&data);
-            }
+            stat.stat_reader(BufReader::new(f))
+                .expect("file read error");
             _tx.send(stat).expect("send error");
         });
     }
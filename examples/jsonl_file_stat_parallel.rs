@@ -0,0 +1,14 @@
+use std::env;
+
+use jsonstat::{JsonStat, JsonStatConfig};
+
+fn main() {
+    if env::args().len() < 2 {
+        println!("Usage: jsonl_file_stat <file>");
+        return;
+    }
+    let files: Vec<String> = env::args().skip(1).collect();
+    let stat = JsonStat::from_jsonl_files_parallel(&files, None, JsonStatConfig::default())
+        .expect("file read error");
+    println!("{}", stat);
+}
@@ -0,0 +1,27 @@
+use std::env;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+
+use jsonstat::{JsonStat, SharedJsonStat};
+use threadpool::ThreadPool;
+
+fn main() {
+    if env::args().len() < 2 {
+        println!("Usage: jsonl_file_stat_shared <file>");
+        return;
+    }
+    let pool = ThreadPool::new(12);
+    let stat = Arc::new(SharedJsonStat::new(JsonStat::new()));
+    for file in env::args().skip(1) {
+        let stat = stat.clone();
+        pool.execute(move || {
+            let f = File::open(file).expect("file open error");
+            for line in BufReader::new(f).lines() {
+                stat.stat_str(&line.expect("file read error"));
+            }
+        });
+    }
+    pool.join();
+    println!("{}", stat.snapshot());
+}
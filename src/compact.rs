@@ -0,0 +1,326 @@
+use std::io::{Read, Write};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{Count, JsonStat, JsonStatError, JsonStatItem, MaxMinCount};
+
+// Layout, all integers little endian:
+//   magic `JSTC`, version byte
+//   u32 group key count, then each group key as u32 length + UTF-8 bytes
+//   record, line and failed line counts as 8 bytes each
+//   u32 key count, then each key as u32 length + UTF-8 bytes
+//   per key, in the same order, a fixed record of the seven type counters
+//   (count/min/max as 8 bytes each where the type has a range, except the 16 byte int
+//   min/max), the 16 byte int sum (JSON values can't hold sums outside the u64 range),
+//   followed by a u32 length + JSON object holding whatever optional per-key state
+//   is left over; what the fixed string/int/float/array ranges have besides
+//   count/min/max (examples, a pending extremes reset) goes in there as
+//   `<type>_extras`
+const MAGIC: &[u8; 4] = b"JSTC";
+const VERSION: u8 = 1;
+const FIXED_FIELDS: [&str; 7] = ["string", "int", "float", "bool", "null", "object", "array"];
+
+#[derive(Serialize, Deserialize)]
+struct Extras {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_example: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min_example: Option<Value>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    extremes_reset: bool,
+}
+
+#[derive(Deserialize)]
+struct FixedExtras {
+    string_extras: Option<Extras>,
+    int_extras: Option<Extras>,
+    float_extras: Option<Extras>,
+    array_extras: Option<Extras>,
+}
+
+fn extras<T>(m: &MaxMinCount<T>) -> Option<Extras> {
+    (m.max_example.is_some() || m.min_example.is_some() || m.extremes_reset).then(|| Extras {
+        max_example: m.max_example.clone(),
+        min_example: m.min_example.clone(),
+        extremes_reset: m.extremes_reset,
+    })
+}
+
+fn set_extras<T>(m: &mut MaxMinCount<T>, extras: Option<Extras>) {
+    if let Some(e) = extras {
+        m.max_example = e.max_example;
+        m.min_example = e.min_example;
+        m.extremes_reset = e.extremes_reset;
+    }
+}
+
+fn write_u32<W: Write>(w: &mut W, n: usize) -> std::io::Result<()> {
+    w.write_all(&(n as u32).to_le_bytes())
+}
+
+fn write_u64<W: Write>(w: &mut W, n: usize) -> std::io::Result<()> {
+    w.write_all(&(n as u64).to_le_bytes())
+}
+
+fn read_bytes<R: Read, const N: usize>(r: &mut R) -> Result<[u8; N], JsonStatError> {
+    let mut buf = [0; N];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_u32<R: Read>(r: &mut R) -> Result<usize, JsonStatError> {
+    Ok(u32::from_le_bytes(read_bytes(r)?) as usize)
+}
+
+fn read_u64<R: Read>(r: &mut R) -> Result<usize, JsonStatError> {
+    Ok(u64::from_le_bytes(read_bytes(r)?) as usize)
+}
+
+fn read_vec<R: Read>(r: &mut R) -> Result<Vec<u8>, JsonStatError> {
+    let len = read_u32(r)?;
+    let mut buf = Vec::new();
+    r.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        return Err(JsonStatError::Format("truncated compact data"));
+    }
+    Ok(buf)
+}
+
+fn read_string<R: Read>(r: &mut R) -> Result<String, JsonStatError> {
+    String::from_utf8(read_vec(r)?).map_err(|_| JsonStatError::Format("key is not valid UTF-8"))
+}
+
+fn write_len_range<W: Write>(w: &mut W, m: &MaxMinCount<usize>) -> std::io::Result<()> {
+    write_u64(w, m.count)?;
+    write_u64(w, m.min)?;
+    write_u64(w, m.max)
+}
+
+fn read_len_range<R: Read>(r: &mut R) -> Result<MaxMinCount<usize>, JsonStatError> {
+    let mut m = MaxMinCount::new();
+    m.count = read_u64(r)?;
+    m.min = read_u64(r)?;
+    m.max = read_u64(r)?;
+    Ok(m)
+}
+
+fn write_item<W: Write>(
+    w: &mut W,
+    item: &JsonStatItem,
+    default: &Value,
+) -> Result<(), JsonStatError> {
+    write_len_range(w, &item.string)?;
+    write_u64(w, item.int.count)?;
+    w.write_all(&item.int.min.to_le_bytes())?;
+    w.write_all(&item.int.max.to_le_bytes())?;
+    write_u64(w, item.float.count)?;
+    w.write_all(&item.float.min.to_le_bytes())?;
+    w.write_all(&item.float.max.to_le_bytes())?;
+    write_u64(w, item.bool.count)?;
+    write_u64(w, item.null.count)?;
+    write_u64(w, item.object.count)?;
+    write_len_range(w, &item.array)?;
+    w.write_all(&item.int_sum.to_le_bytes())?;
+    let mut rest = match serde_json::to_value(JsonStatItem {
+        int_sum: 0,
+        ..item.clone()
+    })? {
+        Value::Object(map) => map,
+        _ => unreachable!("stat items serialize as objects"),
+    };
+    // fields still at their default (zero counters, empty moments) don't need to be
+    // stored; a field that's only serialized when set, like `first_seen`, always is
+    rest.retain(|k, v| !FIXED_FIELDS.contains(&k.as_str()) && default.get(k) != Some(v));
+    for (name, e) in [
+        ("string_extras", extras(&item.string)),
+        ("int_extras", extras(&item.int)),
+        ("float_extras", extras(&item.float)),
+        ("array_extras", extras(&item.array)),
+    ] {
+        if let Some(e) = e {
+            rest.insert(name.to_string(), serde_json::to_value(e)?);
+        }
+    }
+    if rest.is_empty() {
+        write_u32(w, 0)?;
+    } else {
+        let rest = serde_json::to_vec(&rest)?;
+        write_u32(w, rest.len())?;
+        w.write_all(&rest)?;
+    }
+    Ok(())
+}
+
+fn read_item<R: Read>(r: &mut R) -> Result<JsonStatItem, JsonStatError> {
+    let string = read_len_range(r)?;
+    let mut int = MaxMinCount::new();
+    int.count = read_u64(r)?;
+    int.min = i128::from_le_bytes(read_bytes(r)?);
+    int.max = i128::from_le_bytes(read_bytes(r)?);
+    let mut float = MaxMinCount::new();
+    float.count = read_u64(r)?;
+    float.min = f64::from_le_bytes(read_bytes(r)?);
+    float.max = f64::from_le_bytes(read_bytes(r)?);
+    let bool = Count {
+        count: read_u64(r)?,
+    };
+    let null = Count {
+        count: read_u64(r)?,
+    };
+    let object = Count {
+        count: read_u64(r)?,
+    };
+    let array = read_len_range(r)?;
+    let int_sum = i128::from_le_bytes(read_bytes(r)?);
+    let rest = read_vec(r)?;
+    let (mut item, fixed_extras) = if rest.is_empty() {
+        (JsonStatItem::new(), None)
+    } else {
+        (
+            serde_json::from_slice(&rest)?,
+            Some(serde_json::from_slice::<FixedExtras>(&rest)?),
+        )
+    };
+    item.string = string;
+    item.int = int;
+    item.float = float;
+    item.bool = bool;
+    item.null = null;
+    item.object = object;
+    item.array = array;
+    if let Some(e) = fixed_extras {
+        set_extras(&mut item.string, e.string_extras);
+        set_extras(&mut item.int, e.int_extras);
+        set_extras(&mut item.float, e.float_extras);
+        set_extras(&mut item.array, e.array_extras);
+    }
+    item.int_sum = int_sum;
+    Ok(item)
+}
+
+impl JsonStat {
+    pub fn write_compact<W: Write>(&self, mut w: W) -> Result<(), JsonStatError> {
+        w.write_all(MAGIC)?;
+        w.write_all(&[VERSION])?;
+        write_u32(&mut w, self.group_keys.len())?;
+        for k in self.group_keys.iter() {
+            write_u32(&mut w, k.len())?;
+            w.write_all(k.as_bytes())?;
+        }
+        write_u64(&mut w, self.record_count)?;
+        write_u64(&mut w, self.line_count)?;
+        write_u64(&mut w, self.failed_line_count)?;
+        write_u32(&mut w, self.items.len())?;
+        for k in self.items.keys() {
+            write_u32(&mut w, k.len())?;
+            w.write_all(k.as_bytes())?;
+        }
+        let default = serde_json::to_value(JsonStatItem::new())?;
+        for v in self.items.values() {
+            write_item(&mut w, v, &default)?;
+        }
+        Ok(())
+    }
+    pub fn read_compact<R: Read>(mut r: R) -> Result<JsonStat, JsonStatError> {
+        if &read_bytes::<_, 4>(&mut r)? != MAGIC {
+            return Err(JsonStatError::Format("not compact jsonstat data"));
+        }
+        if read_bytes::<_, 1>(&mut r)?[0] != VERSION {
+            return Err(JsonStatError::Format("unsupported compact version"));
+        }
+        let mut ret = JsonStat::new();
+        // counts come from the input, so nothing is allocated up front based on them
+        for _ in 0..read_u32(&mut r)? {
+            ret.group_keys.push(read_string(&mut r)?);
+        }
+        ret.record_count = read_u64(&mut r)?;
+        ret.line_count = read_u64(&mut r)?;
+        ret.failed_line_count = read_u64(&mut r)?;
+        let mut keys = Vec::new();
+        for _ in 0..read_u32(&mut r)? {
+            keys.push(read_string(&mut r)?);
+        }
+        for k in keys {
+            ret.items.insert(k, read_item(&mut r)?);
+        }
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::JsonStatConfig;
+    use serde_json::json;
+    fn round_trip(stat: &JsonStat) -> (Vec<u8>, JsonStat) {
+        let mut buf = Vec::new();
+        stat.write_compact(&mut buf).unwrap();
+        let read = JsonStat::read_compact(buf.as_slice()).unwrap();
+        (buf, read)
+    }
+
+    #[test]
+    fn large_profile_round_trips_smaller_than_json() {
+        let mut stat = JsonStat::new_by_group_with_config(
+            "kind",
+            JsonStatConfig {
+                track_extreme_examples: true,
+                ..Default::default()
+            },
+        );
+        for i in 0..2000 {
+            stat.stat_value(&json!({
+                "kind": format!("k{}", i % 20),
+                "id": i,
+                "score": i as f64 / 3.0,
+                "tags": ["a", "bb"],
+                "nested": { "flag": i % 2 == 0, "note": null },
+                "big": u64::MAX,
+            }));
+        }
+        stat.stat_str("not json");
+        let (buf, read) = round_trip(&stat);
+        assert_eq!(read.to_json_str(true), stat.to_json_str(true));
+        assert_eq!(read.to_json_str(false), stat.to_json_str(false));
+        assert_eq!(read.group_keys, stat.group_keys);
+        assert_eq!(read.record_count(), 2000);
+        assert_eq!(read.line_count(), 1);
+        assert_eq!(read.failed_line_count(), 1);
+        assert!(buf.len() < stat.to_json_str(true).len());
+    }
+
+    #[test]
+    fn reset_extremes_round_trip() {
+        let mut stat = JsonStat::new();
+        stat.stat_value(&json!({"x": 1, "s": "abc"}));
+        stat.reset_extremes();
+        let (_, mut read) = round_trip(&stat);
+        read.stat_value(&json!({"x": 5, "s": "z"}));
+        assert_eq!(read.get(".x").unwrap().int_range(), Some((5, 5)));
+        assert_eq!(read.get(".s").unwrap().string_len_range(), Some((1, 1)));
+    }
+
+    #[test]
+    fn bad_input_is_an_error() {
+        assert!(matches!(
+            JsonStat::read_compact(&b"JSON\x01"[..]),
+            Err(JsonStatError::Format(_))
+        ));
+        assert!(matches!(
+            JsonStat::read_compact(&b"JSTC\x02"[..]),
+            Err(JsonStatError::Format(_))
+        ));
+        // huge counts in the header run out of input instead of allocating for them
+        let mut huge = b"JSTC\x01".to_vec();
+        huge.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(JsonStat::read_compact(huge.as_slice()).is_err());
+        huge.truncate(5);
+        huge.extend_from_slice(&1u32.to_le_bytes());
+        huge.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            JsonStat::read_compact(huge.as_slice()),
+            Err(JsonStatError::Format(_))
+        ));
+    }
+}
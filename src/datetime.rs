@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+
+// ordered by instant first; the original text only breaks ties between equal instants
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, PartialOrd)]
+pub(crate) struct Timestamp {
+    seconds: i64,
+    nanos: u32,
+    pub(crate) text: String,
+}
+
+impl Timestamp {
+    pub(crate) fn redacted(text: String) -> Self {
+        Timestamp {
+            seconds: 0,
+            nanos: 0,
+            text,
+        }
+    }
+}
+
+fn number(s: &[u8]) -> Option<u32> {
+    if s.is_empty() || !s.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    s.iter().try_fold(0u32, |n, c| {
+        n.checked_mul(10)?.checked_add((c - b'0') as u32)
+    })
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// days since 1970-01-01 in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+// accepts `YYYY-MM-DD`, optionally followed by `THH:MM[:SS[.fff]]` (or a space instead
+// of `T`) and a `Z` / `±HH[:MM]` offset
+fn parse_seconds(s: &[u8]) -> Option<(i64, u32)> {
+    if s.len() < 10 || s[4] != b'-' || s[7] != b'-' {
+        return None;
+    }
+    let year = number(&s[0..4])? as i64;
+    let month = number(&s[5..7])?;
+    let day = number(&s[8..10])?;
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    let mut seconds = days_from_civil(year, month, day) * 86400;
+    let mut nanos = 0;
+    let mut rest = &s[10..];
+    if rest.is_empty() {
+        return Some((seconds, nanos));
+    }
+    if !matches!(rest[0], b'T' | b't' | b' ') || rest.len() < 6 || rest[3] != b':' {
+        return None;
+    }
+    let hour = number(&rest[1..3])?;
+    let minute = number(&rest[4..6])?;
+    rest = &rest[6..];
+    let mut second = 0;
+    if rest.first() == Some(&b':') {
+        second = number(rest.get(1..3)?)?;
+        rest = &rest[3..];
+        if rest.first() == Some(&b'.') {
+            let digits = rest[1..].iter().take_while(|c| c.is_ascii_digit()).count();
+            if digits == 0 {
+                return None;
+            }
+            let frac = &rest[1..1 + digits.min(9)];
+            nanos = number(frac)? * 10u32.pow(9 - frac.len() as u32);
+            rest = &rest[1 + digits..];
+        }
+    }
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    seconds += (hour * 3600 + minute * 60 + second) as i64;
+    match rest {
+        [] | [b'Z'] | [b'z'] => {}
+        [sign @ (b'+' | b'-'), offset @ ..] => {
+            let (h, m) = match offset {
+                [h1, h2] => (number(&[*h1, *h2])?, 0),
+                [h1, h2, b':', m1, m2] | [h1, h2, m1, m2] => {
+                    (number(&[*h1, *h2])?, number(&[*m1, *m2])?)
+                }
+                _ => return None,
+            };
+            if h > 23 || m > 59 {
+                return None;
+            }
+            let offset = (h * 3600 + m * 60) as i64;
+            seconds += if *sign == b'+' { -offset } else { offset };
+        }
+        _ => return None,
+    }
+    Some((seconds, nanos))
+}
+
+pub(crate) fn parse_iso8601(s: &str) -> Option<Timestamp> {
+    let (seconds, nanos) = parse_seconds(s.as_bytes())?;
+    Some(Timestamp {
+        seconds,
+        nanos,
+        text: s.to_string(),
+    })
+}
+
+pub(crate) fn is_iso8601(s: &str) -> bool {
+    parse_seconds(s.as_bytes()).is_some()
+}
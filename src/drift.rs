@@ -0,0 +1,286 @@
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::{JsonStat, JsonStatItem};
+
+#[derive(Clone, Debug)]
+pub struct DriftThresholds {
+    /// Report a key when its null ratio moved by more than this (absolute, 0..1).
+    pub null_ratio: f64,
+    /// Report a range when it grew past the baseline by more than this fraction of the
+    /// baseline's width. `0.0` reports any growth.
+    pub range_expansion: f64,
+}
+impl Default for DriftThresholds {
+    fn default() -> Self {
+        Self {
+            null_ratio: 0.1,
+            range_expansion: 0.0,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct TypeChange {
+    pub key: String,
+    pub added: Vec<&'static str>,
+    pub removed: Vec<&'static str>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct RangeExpansion {
+    pub key: String,
+    pub type_name: &'static str,
+    pub baseline_min: f64,
+    pub baseline_max: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct NullRatioShift {
+    pub key: String,
+    pub baseline: f64,
+    pub current: f64,
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct DriftReport {
+    pub new_keys: Vec<String>,
+    pub removed_keys: Vec<String>,
+    pub type_changes: Vec<TypeChange>,
+    pub range_expansions: Vec<RangeExpansion>,
+    pub null_ratio_shifts: Vec<NullRatioShift>,
+}
+impl DriftReport {
+    pub fn is_empty(&self) -> bool {
+        self.new_keys.is_empty()
+            && self.removed_keys.is_empty()
+            && self.type_changes.is_empty()
+            && self.range_expansions.is_empty()
+            && self.null_ratio_shifts.is_empty()
+    }
+}
+
+fn ranges(item: &JsonStatItem) -> [(&'static str, Option<(f64, f64)>); 4] {
+    let usizes = |(min, max): (usize, usize)| (min as f64, max as f64);
+    [
+        (
+            "int",
+            item.int
+                .extremes()
+                .map(|(min, max)| (min as f64, max as f64)),
+        ),
+        ("float", item.float.extremes()),
+        ("string", item.string.extremes().map(usizes)),
+        ("array", item.array.extremes().map(usizes)),
+    ]
+}
+
+// like `ranges`, keeping each range in its own JSON number type
+fn json_ranges(item: &JsonStatItem) -> [(&'static str, Option<(Value, Value)>); 4] {
+    fn to_json<T: Serialize>((min, max): (T, T)) -> (Value, Value) {
+        (json!(min), json!(max))
+    }
+    [
+        ("int", item.int.extremes().map(to_json)),
+        ("float", item.float.extremes().map(to_json)),
+        ("string", item.string.extremes().map(to_json)),
+        ("array", item.array.extremes().map(to_json)),
+    ]
+}
+
+fn count_change(before: usize, after: usize) -> Value {
+    json!({"before": before, "after": after, "delta": after as i128 - before as i128})
+}
+
+impl JsonStat {
+    /// thresholds come from this instance's `JsonStatConfig::drift`
+    pub fn drift_against(&self, baseline: &Self) -> DriftReport {
+        let thresholds = &self.config.drift;
+        let mut ret = DriftReport::default();
+        for k in baseline.items.keys() {
+            if !self.items.contains_key(k) {
+                ret.removed_keys.push(k.clone());
+            }
+        }
+        for (k, v) in self.items.iter() {
+            let base = match baseline.items.get(k) {
+                Some(base) => base,
+                None => {
+                    ret.new_keys.push(k.clone());
+                    continue;
+                }
+            };
+            let mut change = TypeChange {
+                key: k.clone(),
+                added: Vec::new(),
+                removed: Vec::new(),
+            };
+            for ((name, count), (_, base_count)) in v.type_counts().iter().zip(base.type_counts()) {
+                if *count > 0 && base_count == 0 {
+                    change.added.push(name);
+                } else if *count == 0 && base_count > 0 {
+                    change.removed.push(name);
+                }
+            }
+            if !change.added.is_empty() || !change.removed.is_empty() {
+                ret.type_changes.push(change);
+            }
+            for ((name, range), (_, base_range)) in ranges(v).into_iter().zip(ranges(base)) {
+                let ((min, max), (base_min, base_max)) = match (range, base_range) {
+                    (Some(range), Some(base_range)) => (range, base_range),
+                    _ => continue,
+                };
+                let tolerance = (base_max - base_min) * thresholds.range_expansion;
+                if min < base_min - tolerance || max > base_max + tolerance {
+                    ret.range_expansions.push(RangeExpansion {
+                        key: k.clone(),
+                        type_name: name,
+                        baseline_min: base_min,
+                        baseline_max: base_max,
+                        min,
+                        max,
+                    });
+                }
+            }
+            let (current, base) = (v.null_ratio(), base.null_ratio());
+            if (current - base).abs() > thresholds.null_ratio {
+                ret.null_ratio_shifts.push(NullRatioShift {
+                    key: k.clone(),
+                    baseline: base,
+                    current,
+                });
+            }
+        }
+        ret
+    }
+    /// Key-by-key comparison from this instance to `other`: keys only `other` has are
+    /// `added`, keys only this one has are `removed`, and keys in both are listed under
+    /// `changed` with whichever counts and min/max ranges differ.
+    pub fn diff(&self, other: &Self) -> Value {
+        let added: Vec<&String> = other
+            .items
+            .keys()
+            .filter(|k| !self.items.contains_key(*k))
+            .collect();
+        let removed: Vec<&String> = self
+            .items
+            .keys()
+            .filter(|k| !other.items.contains_key(*k))
+            .collect();
+        let mut changed = Map::new();
+        for (k, before) in self.items.iter() {
+            let after = match other.items.get(k) {
+                Some(after) => after,
+                None => continue,
+            };
+            let mut change = Map::new();
+            if before.total_count() != after.total_count() {
+                change.insert(
+                    "count".to_string(),
+                    count_change(before.total_count(), after.total_count()),
+                );
+            }
+            let mut types = Map::new();
+            for ((name, b), (_, a)) in before.type_counts().iter().zip(after.type_counts()) {
+                if *b != a {
+                    types.insert(name.to_string(), count_change(*b, a));
+                }
+            }
+            if !types.is_empty() {
+                change.insert("types".to_string(), Value::Object(types));
+            }
+            let mut ranges = Map::new();
+            for ((name, b_range), (_, a_range)) in
+                json_ranges(before).into_iter().zip(json_ranges(after))
+            {
+                if let (Some(b_range), Some(a_range)) = (b_range, a_range) {
+                    if b_range != a_range {
+                        ranges.insert(
+                            name.to_string(),
+                            json!({"before": [b_range.0, b_range.1], "after": [a_range.0, a_range.1]}),
+                        );
+                    }
+                }
+            }
+            if !ranges.is_empty() {
+                change.insert("ranges".to_string(), Value::Object(ranges));
+            }
+            if !change.is_empty() {
+                changed.insert(k.clone(), Value::Object(change));
+            }
+        }
+        json!({"added": added, "removed": removed, "changed": changed})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::JsonStatConfig;
+    fn stat(lines: &[&str]) -> JsonStat {
+        let mut stat = JsonStat::new();
+        for line in lines {
+            stat.stat_str(line);
+        }
+        stat
+    }
+
+    #[test]
+    fn null_ratio_jump_is_reported() {
+        let baseline = stat(&[
+            r#"{"a": 1, "b": 1}"#,
+            r#"{"a": 2, "b": 2}"#,
+            r#"{"a": 3, "b": null}"#,
+            r#"{"a": 4, "b": 4}"#,
+        ]);
+        let current = stat(&[
+            r#"{"a": 2, "b": null}"#,
+            r#"{"a": 3, "b": null}"#,
+            r#"{"a": 4, "b": 4}"#,
+            r#"{"a": 1, "b": null}"#,
+        ]);
+        let report = current.drift_against(&baseline);
+        assert_eq!(
+            report.null_ratio_shifts,
+            [NullRatioShift {
+                key: ".b".to_string(),
+                baseline: 0.25,
+                current: 0.75,
+            }]
+        );
+        assert!(report.range_expansions.is_empty());
+        assert!(report.type_changes.is_empty());
+        // baselines aren't touched by the comparison
+        assert!(baseline.drift_against(&baseline).is_empty());
+    }
+
+    #[test]
+    fn thresholds_come_from_the_current_config() {
+        let baseline = stat(&[r#"{"a": 0}"#, r#"{"a": 10}"#, r#"{"a": null}"#]);
+        let mut current = JsonStat::new_with_config(JsonStatConfig {
+            drift: DriftThresholds {
+                null_ratio: 0.5,
+                range_expansion: 0.5,
+            },
+            ..Default::default()
+        });
+        for line in [r#"{"a": -4}"#, r#"{"a": 14}"#, r#"{"a": "x"}"#] {
+            current.stat_str(line);
+        }
+        let report = current.drift_against(&baseline);
+        assert!(report.null_ratio_shifts.is_empty());
+        assert!(report.range_expansions.is_empty());
+        assert_eq!(
+            report.type_changes,
+            [TypeChange {
+                key: ".a".to_string(),
+                added: vec!["string"],
+                removed: vec!["null"],
+            }]
+        );
+        current.stat_str(r#"{"a": 16}"#);
+        assert_eq!(current.drift_against(&baseline).range_expansions.len(), 1);
+    }
+}
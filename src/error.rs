@@ -0,0 +1,70 @@
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub enum JsonStatError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    #[cfg(feature = "msgpack")]
+    MsgPack(rmp_serde::decode::Error),
+    #[cfg(feature = "regex")]
+    Regex(regex::Error),
+    Format(&'static str),
+    GroupKeyMismatch(Vec<String>, Vec<String>),
+}
+
+impl Display for JsonStatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonStatError::Io(e) => write!(f, "io error: {}", e),
+            JsonStatError::Json(e) => write!(f, "json error: {}", e),
+            #[cfg(feature = "msgpack")]
+            JsonStatError::MsgPack(e) => write!(f, "msgpack error: {}", e),
+            #[cfg(feature = "regex")]
+            JsonStatError::Regex(e) => write!(f, "regex error: {}", e),
+            JsonStatError::Format(e) => write!(f, "format error: {}", e),
+            JsonStatError::GroupKeyMismatch(a, b) => {
+                write!(f, "group keys differ: {:?} vs {:?}", a, b)
+            }
+        }
+    }
+}
+
+impl std::error::Error for JsonStatError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            JsonStatError::Io(e) => Some(e),
+            JsonStatError::Json(e) => Some(e),
+            #[cfg(feature = "msgpack")]
+            JsonStatError::MsgPack(e) => Some(e),
+            #[cfg(feature = "regex")]
+            JsonStatError::Regex(e) => Some(e),
+            JsonStatError::Format(_) | JsonStatError::GroupKeyMismatch(..) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for JsonStatError {
+    fn from(e: std::io::Error) -> Self {
+        JsonStatError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for JsonStatError {
+    fn from(e: serde_json::Error) -> Self {
+        JsonStatError::Json(e)
+    }
+}
+
+#[cfg(feature = "msgpack")]
+impl From<rmp_serde::decode::Error> for JsonStatError {
+    fn from(e: rmp_serde::decode::Error) -> Self {
+        JsonStatError::MsgPack(e)
+    }
+}
+
+#[cfg(feature = "regex")]
+impl From<regex::Error> for JsonStatError {
+    fn from(e: regex::Error) -> Self {
+        JsonStatError::Regex(e)
+    }
+}
@@ -0,0 +1,56 @@
+use crate::datetime;
+
+// a format is reported as dominant once it covers at least this share of a key's strings
+pub(crate) const DOMINANT_FORMAT_RATIO: f64 = 0.9;
+
+fn is_uuid(s: &[u8]) -> bool {
+    s.len() == 36
+        && s.iter().enumerate().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => *c == b'-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+fn is_email(s: &str) -> bool {
+    let (local, domain) = match s.split_once('@') {
+        Some(parts) => parts,
+        None => return false,
+    };
+    !local.is_empty()
+        && !domain.contains('@')
+        && !s.contains(char::is_whitespace)
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+}
+
+// `scheme://host...` with an RFC 3986 scheme
+fn is_url(s: &str) -> bool {
+    let (scheme, rest) = match s.split_once("://") {
+        Some(parts) => parts,
+        None => return false,
+    };
+    scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        && !rest.is_empty()
+        && !rest.starts_with('/')
+        && !s.contains(char::is_whitespace)
+}
+
+// The well-known format `s` is written in, if any: `date` and `datetime` (ISO-8601),
+// `uuid`, `email` or `url`.
+pub(crate) fn detect(s: &str) -> Option<&'static str> {
+    if is_uuid(s.as_bytes()) {
+        Some("uuid")
+    } else if datetime::is_iso8601(s) {
+        Some(if s.len() == 10 { "date" } else { "datetime" })
+    } else if is_url(s) {
+        Some("url")
+    } else if is_email(s) {
+        Some("email")
+    } else {
+        None
+    }
+}
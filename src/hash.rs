@@ -0,0 +1,151 @@
+use std::hash::{Hash, Hasher};
+
+use serde_json::Value;
+
+/// Hash a value so that equal JSON compares equal regardless of representation: object
+/// members are visited in key order, and integral numbers hash the same whether they
+/// were written as `1`, `1.0` or `-0.0` (for zero).
+pub fn canonical_hash<H: Hasher>(value: &Value, state: &mut H) {
+    match value {
+        Value::Null => 0u8.hash(state),
+        Value::Bool(b) => {
+            1u8.hash(state);
+            b.hash(state);
+        }
+        Value::Number(n) => {
+            2u8.hash(state);
+            if let Some(i) = n.as_i64() {
+                (i as i128).hash(state);
+            } else if let Some(u) = n.as_u64() {
+                (u as i128).hash(state);
+            } else if let Some(f) = n.as_f64() {
+                if f.fract() == 0.0 && f >= i128::MIN as f64 && f <= i128::MAX as f64 {
+                    (f as i128).hash(state);
+                } else {
+                    f.to_bits().hash(state);
+                }
+            }
+        }
+        Value::String(s) => {
+            3u8.hash(state);
+            s.hash(state);
+        }
+        Value::Array(arr) => {
+            4u8.hash(state);
+            (arr.len() as u64).hash(state);
+            for v in arr {
+                canonical_hash(v, state);
+            }
+        }
+        Value::Object(obj) => {
+            5u8.hash(state);
+            (obj.len() as u64).hash(state);
+            let mut members: Vec<_> = obj.iter().collect();
+            members.sort_by(|a, b| a.0.cmp(b.0));
+            for (k, v) in members {
+                k.hash(state);
+                canonical_hash(v, state);
+            }
+        }
+    }
+}
+
+// FNV-1a, 64 bit, with murmur3's finalizer on top since HLL reads the high bits and
+// FNV leaves them poorly mixed for short input. Unlike the std `DefaultHasher` its
+// output is fixed, so sketches built by different builds or machines can be merged;
+// integers are fed little-endian for the same reason.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        let mut h = self.0;
+        h ^= h >> 33;
+        h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        h ^= h >> 33;
+        h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        h ^ (h >> 33)
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 = (self.0 ^ *b as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_i128(&mut self, i: i128) {
+        self.write(&i.to_le_bytes());
+    }
+}
+
+pub(crate) fn default_value_hash(value: &Value) -> u64 {
+    let mut hasher = Fnv1a::default();
+    canonical_hash(value, &mut hasher);
+    hasher.finish()
+}
+
+pub(crate) fn hash_str(s: &str) -> u64 {
+    let mut hasher = Fnv1a::default();
+    hasher.write(s.as_bytes());
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{JsonStat, JsonStatConfig};
+    use serde_json::json;
+
+    #[test]
+    fn reordered_keys_and_number_forms_hash_equal() {
+        let a = json!({"a": 1, "b": [2, {"c": null}]});
+        let b: Value = serde_json::from_str(r#"{"b": [2.0, {"c": null}], "a": 1}"#).unwrap();
+        assert_eq!(default_value_hash(&a), default_value_hash(&b));
+        assert_ne!(
+            default_value_hash(&json!("1")),
+            default_value_hash(&json!(1))
+        );
+    }
+
+    #[test]
+    fn default_hash_is_pinned() {
+        // merged sketches rely on every build producing these exact values
+        assert_eq!(default_value_hash(&json!(null)), 13331581593930102267);
+        assert_eq!(
+            default_value_hash(&json!({"a": [1, "x"]})),
+            1609237223468274868
+        );
+    }
+
+    #[test]
+    fn reordered_objects_count_as_one_distinct_value() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            distinct: true,
+            ..Default::default()
+        });
+        stat.stat_str(r#"{"v": {"a": 1, "b": 2}}"#);
+        stat.stat_str(r#"{"v": {"b": 2, "a": 1}}"#);
+        let summary: Value = serde_json::from_str(&stat.to_json_str(false)).unwrap();
+        assert_eq!(summary[".v"]["distinct"], 1);
+    }
+
+    #[test]
+    fn custom_value_hasher_is_used() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            distinct: true,
+            value_hasher: Some(|_| 7),
+            ..Default::default()
+        });
+        for i in 0..100 {
+            stat.stat_value(&json!({ "v": i }));
+        }
+        let summary: Value = serde_json::from_str(&stat.to_json_str(false)).unwrap();
+        assert_eq!(summary[".v"]["distinct"], 1);
+    }
+}
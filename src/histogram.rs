@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+// values buffered per key to pick an automatic range from
+const AUTO_RANGE_SAMPLE: usize = 100;
+
+/// Equal-width buckets for numeric histograms, shared by every numeric key.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistogramConfig {
+    pub buckets: usize,
+    /// Values below/above the range land in the first/last bucket. `None` takes each
+    /// key's range from its first values, so separately collected partials end up
+    /// with different boundaries and can only be merged approximately; give a range
+    /// when partials are merged, as in the multithreaded example.
+    pub range: Option<(f64, f64)>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct Histogram {
+    min: f64,
+    max: f64,
+    buckets: usize,
+    counts: Vec<usize>,
+    // values, with their counts, seen while an automatic range is still being picked
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pending: Vec<(f64, usize)>,
+}
+impl Histogram {
+    pub(crate) fn new(config: &HistogramConfig) -> Self {
+        let buckets = config.buckets.max(1);
+        let (min, max, counts) = match config.range {
+            Some((min, max)) => (min, max, vec![0; buckets]),
+            None => (0.0, 0.0, Vec::new()),
+        };
+        Self {
+            min,
+            max,
+            buckets,
+            counts,
+            pending: Vec::new(),
+        }
+    }
+    fn is_ranged(&self) -> bool {
+        !self.counts.is_empty()
+    }
+    pub(crate) fn add(&mut self, x: f64) {
+        if self.is_ranged() {
+            self.add_count(x, 1);
+            return;
+        }
+        self.pending.push((x, 1));
+        if self.pending.len() >= AUTO_RANGE_SAMPLE {
+            self.settle();
+        }
+    }
+    fn add_count(&mut self, x: f64, count: usize) {
+        let n = self.counts.len();
+        let width = (self.max - self.min) / n as f64;
+        let index = if width > 0.0 {
+            ((x - self.min) / width).floor().clamp(0.0, (n - 1) as f64) as usize
+        } else if x > self.max {
+            n - 1
+        } else {
+            0
+        };
+        self.counts[index] += count;
+    }
+    // fixes an automatic range from the buffered values
+    fn settle(&mut self) {
+        if self.is_ranged() || self.pending.is_empty() {
+            return;
+        }
+        let pending = std::mem::take(&mut self.pending);
+        self.min = pending.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+        self.max = pending
+            .iter()
+            .map(|p| p.0)
+            .fold(f64::NEG_INFINITY, f64::max);
+        self.counts = vec![0; self.buckets];
+        for (x, count) in pending {
+            self.add_count(x, count);
+        }
+    }
+    pub(crate) fn merge(&mut self, other: &Self) {
+        let mut other = other.clone();
+        if !self.is_ranged() && !other.is_ranged() {
+            self.pending.extend(other.pending);
+            if self.pending.len() >= AUTO_RANGE_SAMPLE {
+                self.settle();
+            }
+            return;
+        }
+        if !self.is_ranged() {
+            // take over other's boundaries rather than a range from fewer values
+            let pending = std::mem::take(&mut self.pending);
+            *self = other;
+            for (x, count) in pending {
+                self.add_count(x, count);
+            }
+            return;
+        }
+        other.settle();
+        if self.min == other.min && self.max == other.max && self.counts.len() == other.counts.len()
+        {
+            for (c, o) in self.counts.iter_mut().zip(other.counts.iter()) {
+                *c += o;
+            }
+        } else {
+            // different boundaries: move each of other's buckets over by its midpoint
+            let width = (other.max - other.min) / other.buckets as f64;
+            for (i, count) in other.counts.iter().enumerate() {
+                if *count > 0 {
+                    self.add_count(other.min + width * (i as f64 + 0.5), *count);
+                }
+            }
+        }
+    }
+    // every value counted `weight` times over
+    pub(crate) fn scale(&mut self, weight: usize) {
+        self.counts.iter_mut().for_each(|c| *c *= weight);
+        self.pending.iter_mut().for_each(|p| p.1 *= weight);
+    }
+    pub(crate) fn to_json_value(&self) -> Value {
+        let mut settled = self.clone();
+        settled.settle();
+        json!({"min": settled.min, "max": settled.max, "counts": settled.counts})
+    }
+}
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+
+const PRECISION: u32 = 10;
+const REGISTERS: usize = 1 << PRECISION;
+
+// HyperLogLog over 64-bit hashes with 1024 one-byte registers: standard error is
+// about 1.04 / sqrt(1024), i.e. roughly 3.25%
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct HyperLogLog {
+    registers: Vec<u8>,
+}
+impl HyperLogLog {
+    pub(crate) fn new() -> Self {
+        Self {
+            registers: vec![0; REGISTERS],
+        }
+    }
+    pub(crate) fn add_hash(&mut self, hash: u64) {
+        let index = (hash >> (64 - PRECISION)) as usize;
+        // the guard bit caps the rank for hashes whose remaining bits are all zero
+        let rest = (hash << PRECISION) | (1 << (PRECISION - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+    pub(crate) fn merge(&mut self, other: &Self) {
+        for (r, o) in self.registers.iter_mut().zip(other.registers.iter()) {
+            *r = (*r).max(*o);
+        }
+    }
+    pub(crate) fn estimate(&self) -> u64 {
+        let m = REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|r| 2f64.powi(-(*r as i32))).sum();
+        let estimate = alpha * m * m / sum;
+        let zeros = self.registers.iter().filter(|r| **r == 0).count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            // linear counting is more accurate for small cardinalities
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+}
@@ -1,13 +1,63 @@
-use std::{collections::BTreeMap, fmt::Display};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
 
-use serde::Serialize;
+use serde::{ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::{json, Map, Value};
 
-#[derive(Serialize, Clone)]
+mod compact;
+mod datetime;
+mod drift;
+mod error;
+mod format;
+mod glob;
+mod hash;
+mod histogram;
+mod hll;
+#[cfg(feature = "parallel")]
+mod parallel;
+#[cfg(feature = "arrow")]
+mod record_batch;
+mod sample;
+mod schema;
+mod shared;
+mod strict;
+mod summary;
+mod tdigest;
+mod topn;
+
+use datetime::Timestamp;
+pub use drift::{DriftReport, DriftThresholds, NullRatioShift, RangeExpansion, TypeChange};
+pub use error::JsonStatError;
+pub use hash::canonical_hash;
+use histogram::Histogram;
+pub use histogram::HistogramConfig;
+use hll::HyperLogLog;
+use sample::Samples;
+pub use shared::SharedJsonStat;
+pub use summary::{BoolSummary, CountSummary, FloatSummary, IntSummary, KeySummary, LengthSummary};
+use tdigest::TDigest;
+use topn::TopN;
+
+#[derive(Serialize, Deserialize, Clone)]
 struct MaxMinCount<T> {
     count: usize,
     max: T,
     min: T,
+    // the values that set min/max, see `JsonStatConfig::track_extreme_examples`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_example: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min_example: Option<Value>,
+    // set by `reset_extremes`: min/max are stale and the next value replaces both
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    extremes_reset: bool,
 }
 impl<T: PartialOrd + Default + Clone> MaxMinCount<T> {
     fn new() -> Self {
@@ -15,39 +65,144 @@ impl<T: PartialOrd + Default + Clone> MaxMinCount<T> {
             count: 0,
             max: T::default(),
             min: T::default(),
+            max_example: None,
+            min_example: None,
+            extremes_reset: false,
         }
     }
+    fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+    // `None` until a value is seen, and again after `reset_extremes` until the next one
+    fn extremes(&self) -> Option<(T, T)> {
+        (self.count > 0 && !self.extremes_reset).then(|| (self.min.clone(), self.max.clone()))
+    }
     fn add(&mut self, new_value: &T) {
-        if self.count == 0 {
+        self.add_with_example(new_value, None);
+    }
+    // `add`, keeping `example` as the value behind min/max when it becomes one
+    fn add_with_example(&mut self, new_value: &T, example: Option<&Value>) {
+        if self.count == 0 || self.extremes_reset {
             self.max = new_value.clone();
             self.min = new_value.clone();
+            self.max_example = example.cloned();
+            self.min_example = example.cloned();
+            self.extremes_reset = false;
         } else {
             if new_value > &self.max {
                 self.max = new_value.clone();
+                self.max_example = example.cloned();
             }
             if new_value < &self.min {
                 self.min = new_value.clone();
+                self.min_example = example.cloned();
             }
         }
         self.count += 1;
     }
     fn merge(&mut self, other: &Self) {
-        if self.count == 0 {
+        if other.count == 0 {
+            return;
+        }
+        if other.extremes_reset {
+            // only the counts of other are still meaningful
+        } else if self.count == 0 || self.extremes_reset {
             self.max = other.max.clone();
             self.min = other.min.clone();
+            self.max_example = other.max_example.clone();
+            self.min_example = other.min_example.clone();
+            self.extremes_reset = false;
         } else {
             if other.max > self.max {
                 self.max = other.max.clone();
+                self.max_example = other.max_example.clone();
             }
             if other.min < self.min {
                 self.min = other.min.clone();
+                self.min_example = other.min_example.clone();
             }
         }
         self.count += other.count;
     }
+    fn reset_extremes(&mut self) {
+        if self.count > 0 {
+            self.max = T::default();
+            self.min = T::default();
+            self.max_example = None;
+            self.min_example = None;
+            self.extremes_reset = true;
+        }
+    }
+}
+
+impl MaxMinCount<String> {
+    // like `add`, but only allocates when the value becomes a new extreme
+    fn add_str(&mut self, new_value: &str) {
+        if self.count == 0 || self.extremes_reset {
+            self.max = new_value.to_string();
+            self.min = new_value.to_string();
+            self.extremes_reset = false;
+        } else {
+            if new_value > self.max.as_str() {
+                self.max = new_value.to_string();
+            }
+            if new_value < self.min.as_str() {
+                self.min = new_value.to_string();
+            }
+        }
+        self.count += 1;
+    }
+}
+
+fn truncate_chars(s: &str, max_chars: usize) -> &str {
+    match s.char_indices().nth(max_chars) {
+        Some((end, _)) => &s[..end],
+        None => s,
+    }
+}
+
+// Welford's online mean/variance, merged with Chan et al.'s parallel formula
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct Moments {
+    count: usize,
+    mean: f64,
+    m2: f64,
+}
+impl Moments {
+    fn add(&mut self, x: f64) {
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+    fn merge(&mut self, other: &Self) {
+        if other.count == 0 {
+            return;
+        }
+        if self.count == 0 {
+            *self = other.clone();
+            return;
+        }
+        let count = self.count + other.count;
+        let delta = other.mean - self.mean;
+        self.mean += delta * other.count as f64 / count as f64;
+        self.m2 += other.m2 + delta * delta * self.count as f64 * other.count as f64 / count as f64;
+        self.count = count;
+    }
+    // sample variance; a single value has none rather than NaN
+    fn variance(&self) -> f64 {
+        if self.count < 2 {
+            0.0
+        } else {
+            self.m2 / (self.count - 1) as f64
+        }
+    }
+    fn stddev(&self) -> f64 {
+        self.variance().sqrt()
+    }
 }
 
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 struct Count {
     count: usize,
 }
@@ -64,17 +219,190 @@ impl Count {
     }
 }
 
-#[derive(Serialize, Clone)]
-struct JsonStatItem {
+// `Value` has no room for integers outside the i64/u64 range, those become floats
+fn int_json(n: i128) -> Value {
+    i64::try_from(n)
+        .map(Value::from)
+        .or_else(|_| u64::try_from(n).map(Value::from))
+        .unwrap_or_else(|_| Value::from(n as f64))
+}
+
+fn array_depth(arr: &[Value]) -> usize {
+    1 + arr
+        .iter()
+        .filter_map(|v| v.as_array().map(|a| array_depth(a)))
+        .max()
+        .unwrap_or(0)
+}
+
+// (count, (min, max), sum, moments) over ints and finite floats as f64, with the count
+// including nonfinite floats like `float_count` does; no range after `reset_extremes`
+type NumberBucket = (usize, Option<(f64, f64)>, f64, Moments);
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct JsonStatItem {
+    #[serde(default = "MaxMinCount::new")]
     string: MaxMinCount<usize>,
-    int: MaxMinCount<i64>,
+    // i128 so unsigned values above i64::MAX are still integers
+    #[serde(default = "MaxMinCount::new")]
+    int: MaxMinCount<i128>,
+    #[serde(default = "MaxMinCount::new")]
     float: MaxMinCount<f64>,
+    #[serde(default = "Count::new")]
     bool: Count,
+    #[serde(default = "Count::new")]
     null: Count,
+    #[serde(default = "Count::new")]
     object: Count,
+    #[serde(default = "MaxMinCount::new")]
     array: MaxMinCount<usize>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    object_field_counts: BTreeMap<usize, usize>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    array_depths: BTreeMap<usize, usize>,
+    #[serde(
+        default = "MaxMinCount::new",
+        skip_serializing_if = "MaxMinCount::is_empty"
+    )]
+    string_datetime: MaxMinCount<Timestamp>,
+    #[serde(
+        default = "MaxMinCount::new",
+        skip_serializing_if = "MaxMinCount::is_empty"
+    )]
+    string_value: MaxMinCount<String>,
+    #[serde(
+        default = "MaxMinCount::new",
+        skip_serializing_if = "MaxMinCount::is_empty"
+    )]
+    string_chars: MaxMinCount<usize>,
+    // member counts of the objects seen
+    #[serde(
+        default = "MaxMinCount::new",
+        skip_serializing_if = "MaxMinCount::is_empty"
+    )]
+    object_keys: MaxMinCount<usize>,
+    // strings per detected format
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    string_formats: BTreeMap<String, usize>,
+    // strings per matching classifier
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    string_matches: BTreeMap<String, usize>,
+    #[serde(default)]
+    bool_true: usize,
+    #[serde(default)]
+    string_empty: usize,
+    // strings longer than `JsonStatConfig::oversize_string_len`
+    #[serde(default)]
+    string_oversize: usize,
+    // strings that parse as a number, see `JsonStatConfig::detect_numeric_strings`
+    #[serde(default)]
+    string_numeric: usize,
+    #[serde(default)]
+    array_empty: usize,
+    #[serde(default)]
+    object_empty: usize,
+    // parent objects that had this key as a member, once per object even when several
+    // member names fold into the key; see `JsonStat::absent_counts`
+    #[serde(default)]
+    member_count: usize,
+    // i128 so summing many i64 values can't overflow
+    #[serde(default)]
+    int_sum: i128,
+    #[serde(default)]
+    float_sum: f64,
+    // NaN/±inf floats, kept out of the float range, sum and moments
+    #[serde(default)]
+    float_nonfinite: usize,
+    #[serde(default)]
+    int_moments: Moments,
+    #[serde(default)]
+    float_moments: Moments,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    distinct: Option<HyperLogLog>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    top: Option<TopN>,
+    // over ints and floats together
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    number_histogram: Option<Histogram>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    number_quantiles: Option<TDigest>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    samples: Option<Samples>,
+    // indices of the first and last record the key appeared in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    first_seen: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_seen: Option<usize>,
+    // only the type counts are kept, see `JsonStatConfig::mask_keys`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    masked: bool,
 }
 impl JsonStatItem {
+    // Counts every value held `weight` times over, for the single-value items built per
+    // weighted record; extremes, distinct estimates and samples stay as they are.
+    fn scale(&mut self, weight: usize) {
+        for count in [
+            &mut self.string.count,
+            &mut self.int.count,
+            &mut self.float.count,
+            &mut self.bool.count,
+            &mut self.null.count,
+            &mut self.object.count,
+            &mut self.array.count,
+            &mut self.string_datetime.count,
+            &mut self.string_value.count,
+            &mut self.string_chars.count,
+            &mut self.object_keys.count,
+            &mut self.string_empty,
+            &mut self.string_oversize,
+            &mut self.string_numeric,
+            &mut self.bool_true,
+            &mut self.array_empty,
+            &mut self.object_empty,
+            &mut self.member_count,
+            &mut self.float_nonfinite,
+            &mut self.int_moments.count,
+            &mut self.float_moments.count,
+        ] {
+            *count *= weight;
+        }
+        for counts in [&mut self.object_field_counts, &mut self.array_depths] {
+            counts.values_mut().for_each(|c| *c *= weight);
+        }
+        for counts in [&mut self.string_formats, &mut self.string_matches] {
+            counts.values_mut().for_each(|c| *c *= weight);
+        }
+        self.int_sum *= weight as i128;
+        self.float_sum *= weight as f64;
+        self.int_moments.m2 *= weight as f64;
+        self.float_moments.m2 *= weight as f64;
+        if let Some(top) = &mut self.top {
+            top.scale(weight);
+        }
+        if let Some(histogram) = &mut self.number_histogram {
+            histogram.scale(weight);
+        }
+        if let Some(quantiles) = &mut self.number_quantiles {
+            quantiles.scale(weight);
+        }
+    }
+    // drops everything but the count of each type
+    fn mask(&mut self) {
+        let mut masked = JsonStatItem::new();
+        masked.string.count = self.string.count;
+        masked.int.count = self.int.count;
+        masked.float.count = self.float.count;
+        masked.float_nonfinite = self.float_nonfinite;
+        masked.bool.count = self.bool.count;
+        masked.null.count = self.null.count;
+        masked.object.count = self.object.count;
+        masked.array.count = self.array.count;
+        masked.member_count = self.member_count;
+        masked.first_seen = self.first_seen;
+        masked.last_seen = self.last_seen;
+        masked.masked = true;
+        *self = masked;
+    }
     fn new() -> Self {
         JsonStatItem {
             string: MaxMinCount::new(),
@@ -84,6 +412,34 @@ impl JsonStatItem {
             null: Count::new(),
             object: Count::new(),
             array: MaxMinCount::new(),
+            object_field_counts: BTreeMap::new(),
+            array_depths: BTreeMap::new(),
+            string_datetime: MaxMinCount::new(),
+            string_value: MaxMinCount::new(),
+            string_chars: MaxMinCount::new(),
+            object_keys: MaxMinCount::new(),
+            string_formats: BTreeMap::new(),
+            string_matches: BTreeMap::new(),
+            bool_true: 0,
+            string_empty: 0,
+            string_oversize: 0,
+            string_numeric: 0,
+            array_empty: 0,
+            object_empty: 0,
+            member_count: 0,
+            int_sum: 0,
+            float_sum: 0.0,
+            float_nonfinite: 0,
+            int_moments: Moments::default(),
+            float_moments: Moments::default(),
+            distinct: None,
+            top: None,
+            number_histogram: None,
+            number_quantiles: None,
+            samples: None,
+            first_seen: None,
+            last_seen: None,
+            masked: false,
         }
     }
     fn merge(&mut self, other: &Self) {
@@ -94,92 +450,640 @@ impl JsonStatItem {
         self.null.merge(&other.null);
         self.object.merge(&other.object);
         self.array.merge(&other.array);
+        for (len, count) in other.object_field_counts.iter() {
+            *self.object_field_counts.entry(*len).or_insert(0) += count;
+        }
+        for (depth, count) in other.array_depths.iter() {
+            *self.array_depths.entry(*depth).or_insert(0) += count;
+        }
+        self.string_datetime.merge(&other.string_datetime);
+        self.string_value.merge(&other.string_value);
+        self.string_chars.merge(&other.string_chars);
+        self.object_keys.merge(&other.object_keys);
+        for (format, count) in other.string_formats.iter() {
+            *self.string_formats.entry(format.clone()).or_insert(0) += count;
+        }
+        for (name, count) in other.string_matches.iter() {
+            *self.string_matches.entry(name.clone()).or_insert(0) += count;
+        }
+        self.bool_true += other.bool_true;
+        self.string_empty += other.string_empty;
+        self.string_oversize += other.string_oversize;
+        self.string_numeric += other.string_numeric;
+        self.array_empty += other.array_empty;
+        self.object_empty += other.object_empty;
+        self.member_count += other.member_count;
+        self.int_sum += other.int_sum;
+        self.float_sum += other.float_sum;
+        self.float_nonfinite += other.float_nonfinite;
+        self.int_moments.merge(&other.int_moments);
+        self.float_moments.merge(&other.float_moments);
+        match (&mut self.distinct, &other.distinct) {
+            (Some(d), Some(o)) => d.merge(o),
+            (None, Some(o)) => self.distinct = Some(o.clone()),
+            _ => {}
+        }
+        match (&mut self.top, &other.top) {
+            (Some(t), Some(o)) => t.merge(o),
+            (None, Some(o)) => self.top = Some(o.clone()),
+            _ => {}
+        }
+        match (&mut self.number_histogram, &other.number_histogram) {
+            (Some(h), Some(o)) => h.merge(o),
+            (None, Some(o)) => self.number_histogram = Some(o.clone()),
+            _ => {}
+        }
+        match (&mut self.number_quantiles, &other.number_quantiles) {
+            (Some(q), Some(o)) => q.merge(o),
+            (None, Some(o)) => self.number_quantiles = Some(o.clone()),
+            _ => {}
+        }
+        match (&mut self.samples, &other.samples) {
+            (Some(s), Some(o)) => s.merge(o),
+            (None, Some(o)) => self.samples = Some(o.clone()),
+            _ => {}
+        }
+        self.first_seen = match (self.first_seen, other.first_seen) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.last_seen = self.last_seen.max(other.last_seen);
+        if other.masked && !self.masked {
+            self.mask();
+        }
     }
-    fn stat(&mut self, key: &str, data: &Value) -> Vec<(String, Value)> {
-        let mut ret = Vec::new();
+    fn add(&mut self, data: &Value, config: &JsonStatConfig) {
+        if let Some(types) = &config.record_types {
+            if !types.contains(&ValueType::of(data)) {
+                return;
+            }
+        }
+        if config.distinct {
+            self.distinct
+                .get_or_insert_with(HyperLogLog::new)
+                .add_hash(config.hash_value(data));
+        }
+        if let Some(n) = config.top_n {
+            let value = match data {
+                Value::String(s) if s.len() <= config.top_value_max_len() => {
+                    Some(Value::String(config.retain_str(s)))
+                }
+                Value::Number(n) if n.is_i64() || n.is_u64() => Some(config.retain_value(data)),
+                Value::Bool(_) => Some(config.retain_value(data)),
+                _ => None,
+            };
+            if let Some(value) = value {
+                self.top.get_or_insert_with(|| TopN::new(n)).add(value);
+            }
+        }
+        // samples of a container would hold the values of denied and masked keys below it
+        let hides_keys = config.deny_keys.is_some() || config.mask_keys.is_some();
+        if config.samples && !(hides_keys && (data.is_object() || data.is_array())) {
+            self.samples
+                .get_or_insert_with(|| Samples::new(config.sample_size()))
+                .add(config.hash_value(data), || {
+                    if config.redact_values {
+                        config.retain_value(data)
+                    } else {
+                        sample::truncate_sample(data)
+                    }
+                });
+        }
         match data {
             Value::String(s) => {
                 self.string.add(&s.len());
+                if s.is_empty() {
+                    self.string_empty += 1;
+                }
+                if config.oversize_string_len.is_some_and(|max| s.len() > max) {
+                    self.string_oversize += 1;
+                }
+                if config.detect_numeric_strings
+                    && (s.parse::<i128>().is_ok() || s.parse::<f64>().is_ok_and(|x| x.is_finite()))
+                {
+                    self.string_numeric += 1;
+                }
+                if config.string_char_lengths {
+                    self.string_chars.add(&s.chars().count());
+                }
+                if config.redact_values {
+                    self.string_value.add_str(&config.retain_str(s));
+                } else {
+                    self.string_value
+                        .add_str(truncate_chars(s, STRING_VALUE_MAX_CHARS));
+                }
+                if config.detect_formats {
+                    if let Some(format) = format::detect(s) {
+                        *self.string_formats.entry(format.to_string()).or_insert(0) += 1;
+                    }
+                }
+                #[cfg(feature = "regex")]
+                for (name, regex) in config.classifiers.iter() {
+                    if regex.is_match(s) {
+                        *self.string_matches.entry(name.clone()).or_insert(0) += 1;
+                    }
+                }
+                if config.detect_datetime {
+                    if let Some(ts) = datetime::parse_iso8601(s) {
+                        if config.redact_values {
+                            self.string_datetime
+                                .add(&Timestamp::redacted(config.retain_str(s)));
+                        } else {
+                            self.string_datetime.add(&ts);
+                        }
+                    }
+                }
             }
             Value::Number(n) => {
-                if let Some(num) = n.as_i64() {
-                    self.int.add(&num);
+                if let Some(x) = n.as_f64().filter(|x| x.is_finite()) {
+                    if let Some(histogram) = &config.histogram {
+                        self.number_histogram
+                            .get_or_insert_with(|| Histogram::new(histogram))
+                            .add(x);
+                    }
+                    if let Some(compression) = config.quantiles {
+                        self.number_quantiles
+                            .get_or_insert_with(|| TDigest::new(compression))
+                            .add(x);
+                    }
+                }
+                let integral = || {
+                    n.as_f64()
+                        .filter(|f| config.integral_floats_as_int && f.fract() == 0.0)
+                        // the int range only has to hold what i64/u64 can
+                        .filter(|f| *f >= -(2f64.powi(63)) && *f < 2f64.powi(64))
+                        .map(|f| f as i128)
+                };
+                let redacted;
+                let example = match (config.track_extreme_examples, config.redact_values) {
+                    (false, _) => None,
+                    (true, false) => Some(data),
+                    (true, true) => {
+                        redacted = config.retain_value(data);
+                        Some(&redacted)
+                    }
+                };
+                if let Some(num) = n
+                    .as_i64()
+                    .map(i128::from)
+                    .or(n.as_u64().map(i128::from))
+                    .or_else(integral)
+                {
+                    self.int.add_with_example(&num, example);
+                    self.int_sum += num;
+                    self.int_moments.add(num as f64);
                 } else if let Some(num) = n.as_f64() {
-                    self.float.add(&num);
+                    // serde_json itself refuses NaN/±inf, so this only guards against
+                    // numbers built some other way
+                    if num.is_finite() {
+                        self.float.add_with_example(&num, example);
+                        self.float_sum += num;
+                        self.float_moments.add(num);
+                    } else {
+                        self.float_nonfinite += 1;
+                    }
                 }
             }
             Value::Null => {
                 self.null.add();
             }
-            Value::Bool(_) => {
+            Value::Bool(b) => {
                 self.bool.add();
+                if *b {
+                    self.bool_true += 1;
+                }
             }
             Value::Array(arr) => {
                 self.array.add(&arr.len());
-                let k = format!("{}[]", key);
-                for item in arr {
-                    ret.push((k.clone(), item.clone()));
+                if arr.is_empty() {
+                    self.array_empty += 1;
+                }
+                if config.array_depth_histogram {
+                    *self.array_depths.entry(array_depth(arr)).or_insert(0) += 1;
                 }
             }
             Value::Object(obj) => {
                 self.object.add();
+                self.object_keys.add(&obj.len());
+                if obj.is_empty() {
+                    self.object_empty += 1;
+                }
+                if config.object_field_histogram {
+                    *self.object_field_counts.entry(obj.len()).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+    fn stat(&mut self, key: &str, data: &Value, config: &JsonStatConfig) -> Vec<(String, Value)> {
+        self.add(data, config);
+        let mut ret = Vec::new();
+        match data {
+            Value::Array(arr) => {
+                let indexed = config.array_index_limit.unwrap_or(0).min(arr.len());
+                for (i, item) in arr[..indexed].iter().enumerate() {
+                    ret.push((config.indexed_element_key(key, i), item.clone()));
+                }
+                if arr.len() > indexed {
+                    let k = config.element_key(key);
+                    for item in &arr[indexed..] {
+                        ret.push((k.clone(), item.clone()));
+                    }
+                }
+            }
+            Value::Object(obj) => {
                 for (k, v) in obj {
-                    ret.push((format!("{}.{}", key, k), v.clone()));
+                    ret.push((config.member_key(key, k), v.clone()));
                 }
             }
+            _ => {}
         }
         ret
     }
-    fn to_json_value(&self) -> Value {
+    fn reset_extremes(&mut self) {
+        self.string.reset_extremes();
+        self.int.reset_extremes();
+        self.float.reset_extremes();
+        self.array.reset_extremes();
+        self.string_datetime.reset_extremes();
+        self.string_value.reset_extremes();
+        self.string_chars.reset_extremes();
+        self.object_keys.reset_extremes();
+    }
+    pub fn null_count(&self) -> usize {
+        self.null.count
+    }
+    pub fn bool_count(&self) -> usize {
+        self.bool.count
+    }
+    pub fn bool_true_count(&self) -> usize {
+        self.bool_true
+    }
+    pub fn bool_false_count(&self) -> usize {
+        self.bool.count - self.bool_true
+    }
+    pub fn int_count(&self) -> usize {
+        self.int.count
+    }
+    pub fn float_count(&self) -> usize {
+        self.float.count + self.float_nonfinite
+    }
+    pub fn string_count(&self) -> usize {
+        self.string.count
+    }
+    pub fn array_count(&self) -> usize {
+        self.array.count
+    }
+    pub fn object_count(&self) -> usize {
+        self.object.count
+    }
+    /// (min, max) of each ranged type, `None` when the type wasn't seen; string and array
+    /// ranges are lengths
+    pub fn int_range(&self) -> Option<(i128, i128)> {
+        self.int.extremes()
+    }
+    pub fn float_range(&self) -> Option<(f64, f64)> {
+        self.float.extremes()
+    }
+    pub fn string_len_range(&self) -> Option<(usize, usize)> {
+        self.string.extremes()
+    }
+    /// only tracked with `string_char_lengths`
+    pub fn string_char_range(&self) -> Option<(usize, usize)> {
+        self.string_chars.extremes()
+    }
+    pub fn array_len_range(&self) -> Option<(usize, usize)> {
+        self.array.extremes()
+    }
+    pub fn object_key_range(&self) -> Option<(usize, usize)> {
+        self.object_keys.extremes()
+    }
+    pub fn type_counts(&self) -> [(&'static str, usize); 7] {
+        [
+            ("null", self.null.count),
+            ("bool", self.bool.count),
+            ("int", self.int.count),
+            ("float", self.float_count()),
+            ("string", self.string.count),
+            ("array", self.array.count),
+            ("object", self.object.count),
+        ]
+    }
+    pub fn total_count(&self) -> usize {
+        self.type_counts().iter().map(|(_, c)| c).sum()
+    }
+    // the most frequent type; ties go to the earlier type in `type_counts` order, except
+    // that null only wins when it is strictly the most frequent
+    fn dominant_type(&self) -> &'static str {
+        let counts = self.type_counts();
+        let mut ret = ("", 0);
+        for (name, count) in counts[1..].iter().chain(&counts[..1]) {
+            if *count > ret.1 {
+                ret = (name, *count);
+            }
+        }
+        ret.0
+    }
+    fn extremes(&self, type_name: &str) -> Option<(String, String)> {
+        fn to_strings<T: ToString>((min, max): (T, T)) -> (String, String) {
+            (min.to_string(), max.to_string())
+        }
+        match type_name {
+            "int" => self.int.extremes().map(to_strings),
+            "float" => self.float.extremes().map(to_strings),
+            "string" => self.string.extremes().map(to_strings),
+            "array" => self.array.extremes().map(to_strings),
+            _ => None,
+        }
+    }
+    fn range(&self, type_name: &str) -> Option<String> {
+        self.extremes(type_name)
+            .map(|(min, max)| format!("{}~{}", min, max))
+    }
+    // min/max over ints and floats together, each extreme in whichever encoding it was
+    // seen as
+    fn number_range(&self) -> Option<(Value, Value)> {
+        match (self.int.extremes(), self.float.extremes()) {
+            _ if self.masked => None,
+            (None, None) => None,
+            (Some((min, max)), None) => Some((json!(min), json!(max))),
+            (None, Some((min, max))) => Some((json!(min), json!(max))),
+            (Some((int_min, int_max)), Some((float_min, float_max))) => {
+                let min = if (int_min as f64) <= float_min {
+                    json!(int_min)
+                } else {
+                    json!(float_min)
+                };
+                let max = if (int_max as f64) >= float_max {
+                    json!(int_max)
+                } else {
+                    json!(float_max)
+                };
+                Some((min, max))
+            }
+        }
+    }
+    fn null_ratio(&self) -> f64 {
+        self.null.count as f64 / self.total_count() as f64
+    }
+    // ints and finite floats together as f64, see `NumberBucket`
+    fn number_bucket(&self) -> Option<NumberBucket> {
+        if self.masked || self.int.count + self.float.count == 0 {
+            return None;
+        }
+        let range = self.number_range().map(|(min, max)| {
+            (
+                min.as_f64().unwrap_or_default(),
+                max.as_f64().unwrap_or_default(),
+            )
+        });
+        let mut moments = self.int_moments.clone();
+        moments.merge(&self.float_moments);
+        Some((
+            self.int.count + self.float_count(),
+            range,
+            self.int_sum as f64 + self.float_sum,
+            moments,
+        ))
+    }
+    // only meaningful when the matching count is non-zero
+    fn int_mean(&self) -> f64 {
+        self.int_sum as f64 / self.int.count as f64
+    }
+    fn float_mean(&self) -> f64 {
+        self.float_sum / self.float.count as f64
+    }
+    fn is_container_only(&self) -> bool {
+        self.string.count == 0
+            && self.int.count == 0
+            && self.float_count() == 0
+            && self.bool.count == 0
+            && self.null.count == 0
+    }
+    fn to_json_value(&self, config: &JsonStatConfig) -> Value {
         let mut ret = Map::new();
+        if self.masked {
+            for (name, count) in self.type_counts() {
+                if count > 0 || config.verbose {
+                    ret.insert(name.to_string(), json!({ "count": count }));
+                }
+            }
+            ret.insert("masked".to_string(), Value::Bool(true));
+            return Value::Object(ret);
+        }
         if self.null.count > 0 {
             ret.insert("null".to_string(), json!({"count": self.null.count}));
         }
         if self.bool.count > 0 {
-            ret.insert("bool".to_string(), json!({"count": self.bool.count}));
-        }
-        if self.int.count > 0 {
             ret.insert(
-                "int".to_string(),
+                "bool".to_string(),
                 json!({
-                    "count": self.int.count,
-                    "min": self.int.min,
-                    "max": self.int.max,
+                    "count": self.bool.count,
+                    "true": self.bool_true,
+                    "false": self.bool.count - self.bool_true,
                 }),
             );
         }
-        if self.float.count > 0 {
+        let number_bucket = config.number_bucket.then(|| self.number_bucket()).flatten();
+        if let Some((count, range, sum, moments)) = &number_bucket {
+            let mut number = json!({
+                "count": count,
+                "sum": sum,
+                "mean": config.round_float(moments.mean),
+                "variance": moments.variance(),
+                "stddev": moments.stddev(),
+            });
+            if let Some((min, max)) = range {
+                number["min"] = json!(config.round_float(*min));
+                number["max"] = json!(config.round_float(*max));
+            }
+            if self.float_nonfinite > 0 {
+                number["nonfinite"] = json!(self.float_nonfinite);
+            }
+            ret.insert("number".to_string(), number);
+        } else if self.int.count > 0 {
+            let mut int = json!({
+                "count": self.int.count,
+                "sum": int_json(self.int_sum),
+                "mean": config.round_float(self.int_mean()),
+                "variance": self.int_moments.variance(),
+                "stddev": self.int_moments.stddev(),
+            });
+            if let Some((min, max)) = self.int.extremes() {
+                int["min"] = json!(min);
+                int["max"] = json!(max);
+                // integers whose min is 0 and max at most 1 can only have held 0 or 1
+                if min == 0 && max <= 1 {
+                    int["bool_like"] = Value::Bool(true);
+                }
+            }
+            ret.insert("int".to_string(), int);
+        }
+        if number_bucket.is_some() {
+            // already reported as the number bucket
+        } else if self.float.count > 0 {
+            let mut float = json!({
+                "count": self.float_count(),
+                "sum": self.float_sum,
+                "mean": config.round_float(self.float_mean()),
+                "variance": self.float_moments.variance(),
+                "stddev": self.float_moments.stddev(),
+            });
+            if let Some((min, max)) = self.float.extremes() {
+                float["min"] = json!(config.round_float(min));
+                float["max"] = json!(config.round_float(max));
+            }
+            if self.float_nonfinite > 0 {
+                float["nonfinite"] = json!(self.float_nonfinite);
+            }
+            ret.insert("float".to_string(), float);
+        } else if self.float_nonfinite > 0 {
             ret.insert(
                 "float".to_string(),
-                json!({
-                    "count": self.float.count,
-                    "min": self.float.min,
-                    "max": self.float.max,
-                }),
+                json!({"count": self.float_nonfinite, "nonfinite": self.float_nonfinite}),
             );
         }
+        // mixed keys get a combined block, except in verbose output, which keeps to the
+        // same blocks for every key
+        if number_bucket.is_none() && !config.verbose && self.int.count > 0 && self.float.count > 0
+        {
+            let round = |v: Value| match v.as_f64() {
+                Some(x) if v.is_f64() => json!(config.round_float(x)),
+                _ => v,
+            };
+            let mut number = json!({"count": self.int.count + self.float_count()});
+            if let Some((min, max)) = self.number_range() {
+                number["min"] = round(min);
+                number["max"] = round(max);
+            }
+            ret.insert("number".to_string(), number);
+        }
         if self.string.count > 0 {
-            ret.insert(
-                "string".to_string(),
-                json!({
-                    "count": self.string.count,
-                    "min": self.string.min,
-                    "max": self.string.max,
-                }),
-            );
+            let mut string = json!({
+                "count": self.string.count,
+                "empty": self.string_empty,
+            });
+            if let Some((min, max)) = self.string.extremes() {
+                string["min"] = json!(min);
+                string["max"] = json!(max);
+            }
+            if self.string_oversize > 0 {
+                string["oversize"] = json!(self.string_oversize);
+            }
+            if self.string_numeric > 0 {
+                string["numeric_like"] = json!(self.string_numeric);
+            }
+            if let Some((min, max)) = self.string_chars.extremes() {
+                string["min_chars"] = json!(min);
+                string["max_chars"] = json!(max);
+            }
+            if let Some((min, max)) = self.string_value.extremes() {
+                string["min_value"] = json!(min);
+                string["max_value"] = json!(max);
+            }
+            if !self.string_datetime.is_empty() {
+                let mut datetime = json!({"count": self.string_datetime.count});
+                if let Some((min, max)) = self.string_datetime.extremes() {
+                    datetime["min"] = json!(min.text);
+                    datetime["max"] = json!(max.text);
+                }
+                string["datetime"] = datetime;
+            }
+            if !self.string_formats.is_empty() {
+                string["formats"] = json!(self.string_formats);
+                let dominant = self
+                    .string_formats
+                    .iter()
+                    .max_by_key(|(_, count)| **count)
+                    .filter(|(_, count)| {
+                        **count as f64 >= self.string.count as f64 * format::DOMINANT_FORMAT_RATIO
+                    });
+                if let Some((format, _)) = dominant {
+                    string["format"] = json!(format);
+                }
+            }
+            if !self.string_matches.is_empty() {
+                string["matches"] = json!(self.string_matches);
+            }
+            ret.insert("string".to_string(), string);
         }
         if self.array.count > 0 {
-            ret.insert(
-                "array".to_string(),
-                json!({
-                    "count": self.array.count,
-                    "min": self.array.min,
-                    "max": self.array.max,
-                }),
-            );
+            let mut array = json!({
+                "count": self.array.count,
+                "empty": self.array_empty,
+                "always_empty": self.array_empty == self.array.count,
+            });
+            if let Some((min, max)) = self.array.extremes() {
+                array["min"] = json!(min);
+                array["max"] = json!(max);
+            }
+            if !self.array_depths.is_empty() {
+                array["depth_histogram"] = json!(self.array_depths);
+            }
+            ret.insert("array".to_string(), array);
         }
         if self.object.count > 0 {
-            ret.insert("object".to_string(), json!({"count": self.object.count}));
+            let mut object = json!({
+                "count": self.object.count,
+                "empty": self.object_empty,
+                "always_empty": self.object_empty == self.object.count,
+            });
+            // missing from stats deserialized from before member counts were kept
+            if let Some((min, max)) = self.object_keys.extremes() {
+                object["min_keys"] = json!(min);
+                object["max_keys"] = json!(max);
+            }
+            if !self.object_field_counts.is_empty() {
+                object["field_count_histogram"] = json!(self.object_field_counts);
+            }
+            ret.insert("object".to_string(), object);
+        }
+        if config.verbose {
+            for (name, _) in self.type_counts() {
+                let name = match name {
+                    "int" | "float" if config.number_bucket => "number",
+                    _ => name,
+                };
+                ret.entry(name).or_insert_with(|| json!({"count": 0}));
+            }
+        }
+        if let Some(distinct) = &self.distinct {
+            ret.insert("distinct".to_string(), json!(distinct.estimate()));
+        }
+        if let Some(top) = &self.top {
+            ret.insert("top".to_string(), top.to_json_value());
+        }
+        if let Some(histogram) = &self.number_histogram {
+            ret.insert("histogram".to_string(), histogram.to_json_value());
+        }
+        if let Some(quantiles) = &self.number_quantiles {
+            ret.insert("quantiles".to_string(), quantiles.to_json_value());
+        }
+        if let Some(samples) = &self.samples {
+            ret.insert("samples".to_string(), samples.to_json_value());
+        }
+        if config.type_percentages {
+            let total = self.total_count() as f64;
+            let mut counts = self.type_counts().to_vec();
+            if let Some((count, ..)) = &number_bucket {
+                counts.push(("number", *count));
+            }
+            for (name, count) in counts {
+                if let Some(block) = ret.get_mut(name).filter(|_| count > 0) {
+                    block["pct"] = json!((count as f64 / total * 10000.0).round() / 10000.0);
+                }
+            }
+        }
+        if let Some(metrics) = &config.metrics {
+            // only the type blocks; distinct/top/histogram/quantiles/samples stay whole
+            let type_blocks = self.type_counts().map(|(name, _)| name);
+            for (name, block) in ret.iter_mut() {
+                if !(name == "number" || type_blocks.contains(&name.as_str())) {
+                    continue;
+                }
+                if let Some(block) = block.as_object_mut() {
+                    block.retain(|k, _| metrics.iter().any(|m| m == k));
+                }
+            }
         }
         Value::Object(ret)
     }
@@ -187,141 +1091,1669 @@ impl JsonStatItem {
 
 impl Display for JsonStatItem {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.null.count > 0 {
+        self.write_summary(f, &JsonStatConfig::default())
+    }
+}
+impl JsonStatItem {
+    fn write_summary(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        config: &JsonStatConfig,
+    ) -> std::fmt::Result {
+        let (number_bucket, verbose) = (config.number_bucket, config.verbose);
+        if self.masked {
+            for (name, count) in self.type_counts() {
+                if count > 0 || verbose {
+                    write!(f, "{}:{};", name, count)?;
+                }
+            }
+            return write!(f, "masked");
+        }
+        if self.null.count > 0 || verbose {
             write!(f, "null:{};", self.null.count)?;
         }
         if self.bool.count > 0 {
-            write!(f, "bool:{};", self.bool.count)?;
-        }
-        if self.int.count > 0 {
             write!(
                 f,
-                "int:{}({}~{});",
-                self.int.count, self.int.min, self.int.max
+                "bool:{}(true:{},false:{});",
+                self.bool.count,
+                self.bool_true,
+                self.bool.count - self.bool_true
             )?;
+        } else if verbose {
+            write!(f, "bool:0;")?;
         }
-        if self.float.count > 0 {
+        if let (true, Some((count, range, _, moments))) = (number_bucket, self.number_bucket()) {
+            write!(f, "number:{}(", count)?;
+            if let Some((min, max)) = range {
+                write!(
+                    f,
+                    "{}~{},",
+                    config.round_float(min),
+                    config.round_float(max)
+                )?;
+            }
             write!(
                 f,
-                "float:{}({}~{});",
-                self.float.count, self.float.min, self.float.max
+                "mean:{},stddev:{}",
+                config.round_float(moments.mean),
+                moments.stddev()
             )?;
-        }
-        if self.string.count > 0 {
+            if self.float_nonfinite > 0 {
+                write!(f, ",nonfinite:{}", self.float_nonfinite)?;
+            }
+            write!(f, ");")?;
+        } else if self.int.count > 0 {
+            write!(f, "int:{}(", self.int.count)?;
+            if let Some((min, max)) = self.int.extremes() {
+                write!(f, "{}~{},", min, max)?;
+            }
             write!(
                 f,
-                "string:{}({}~{});",
-                self.string.count, self.string.min, self.string.max
+                "mean:{},stddev:{});",
+                config.round_float(self.int_mean()),
+                self.int_moments.stddev()
             )?;
+        } else if verbose {
+            write!(f, "{}:0;", if number_bucket { "number" } else { "int" })?;
         }
-        if self.array.count > 0 {
+        if number_bucket && self.int.count + self.float.count > 0 {
+            // already written as part of the number bucket
+        } else if self.float.count > 0 {
+            write!(f, "float:{}(", self.float_count())?;
+            if let Some((min, max)) = self.float.extremes() {
+                write!(
+                    f,
+                    "{}~{},",
+                    config.round_float(min),
+                    config.round_float(max)
+                )?;
+            }
+            write!(
+                f,
+                "mean:{},stddev:{}",
+                config.round_float(self.float_mean()),
+                self.float_moments.stddev()
+            )?;
+            if self.float_nonfinite > 0 {
+                write!(f, ",nonfinite:{}", self.float_nonfinite)?;
+            }
+            write!(f, ");")?;
+        } else if self.float_nonfinite > 0 {
             write!(
                 f,
-                "array:{}({}~{});",
-                self.array.count, self.array.min, self.array.max
+                "float:{}(nonfinite:{});",
+                self.float_nonfinite, self.float_nonfinite
             )?;
+        } else if verbose && !number_bucket {
+            write!(f, "float:0;")?;
         }
-        if self.object.count > 0 {
+        if let Some((min, max)) = self.string.extremes() {
+            write!(f, "string:{}({}~{});", self.string.count, min, max)?;
+        } else if self.string.count > 0 || verbose {
+            write!(f, "string:{};", self.string.count)?;
+        }
+        if let Some((min, max)) = self.array.extremes() {
+            write!(f, "array:{}({}~{});", self.array.count, min, max)?;
+        } else if self.array.count > 0 || verbose {
+            write!(f, "array:{};", self.array.count)?;
+        }
+        if self.object.count > 0 || verbose {
             write!(f, "object:{}", self.object.count)?;
         }
+        if let Some(distinct) = &self.distinct {
+            if self.object.count > 0 || verbose {
+                write!(f, ";")?;
+            }
+            write!(f, "distinct:{}", distinct.estimate())?;
+        }
         Ok(())
     }
 }
-pub struct JsonStat {
-    items: BTreeMap<String, JsonStatItem>,
-    group_key: Option<String>,
+/// What happens to values for new keys once `key_limit` keys are tracked.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyOverflow {
+    /// Forget them.
+    #[default]
+    Drop,
+    /// Fold them all into one `<overflow>` key.
+    Aggregate,
 }
-impl JsonStat {
-    pub fn new() -> Self {
-        JsonStat {
-            items: BTreeMap::new(),
-            group_key: None,
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValueType {
+    Null,
+    Bool,
+    Number,
+    String,
+    Array,
+    Object,
+}
+impl ValueType {
+    pub fn of(value: &Value) -> Self {
+        match value {
+            Value::Null => ValueType::Null,
+            Value::Bool(_) => ValueType::Bool,
+            Value::Number(_) => ValueType::Number,
+            Value::String(_) => ValueType::String,
+            Value::Array(_) => ValueType::Array,
+            Value::Object(_) => ValueType::Object,
         }
     }
-    pub fn new_by_group(group_key: &str) -> Self {
-        JsonStat {
-            items: BTreeMap::new(),
-            group_key: Some(group_key.to_string()),
+}
+
+// `[N]` at the start of `s`
+fn is_index_suffix(s: &str) -> bool {
+    s.strip_prefix('[')
+        .and_then(|s| s.split_once(']'))
+        .is_some_and(|(digits, _)| !digits.is_empty() && digits.bytes().all(|c| c.is_ascii_digit()))
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+const TRUNCATED_KEY_MARKER: &str = "<truncated>";
+const OVERFLOW_KEY: &str = "<overflow>";
+const MISSING_GROUP_VALUE: &str = "<missing>";
+const NULL_GROUP_VALUE: &str = "<null>";
+// reserved summary output key holding the number of records stat'ed
+const RECORD_COUNT_KEY: &str = "<record_count>";
+const MAX_KEYS_EXCEEDED: &str = "max_keys exceeded";
+// reserved summary output key holding `JsonStatConfig::sample_every`, when set
+const SAMPLE_EVERY_KEY: &str = "<sample_every>";
+// weights are capped here so scaled counts and their sums stay far from overflowing
+const MAX_RECORD_WEIGHT: u64 = u32::MAX as u64;
+const DEFAULT_TOP_VALUE_MAX_LEN: usize = 256;
+const DEFAULT_SAMPLE_SIZE: usize = 3;
+// string min/max values are compared and stored on at most this many leading chars
+const STRING_VALUE_MAX_CHARS: usize = 256;
+
+#[derive(Clone, Default)]
+pub struct JsonStatConfig {
+    /// Key paths longer than this many bytes are cut and suffixed with `<truncated>`,
+    /// so every descendant of an over-long path folds into the same entry.
+    pub max_key_length: Option<usize>,
+    /// Count how many objects at each path had each number of fields.
+    pub object_field_histogram: bool,
+    /// Remember the index of the last record each key appeared in. Order dependent:
+    /// merging appends the other side's records after this one's.
+    pub track_last_seen: bool,
+    /// Keep the index of the first and last record each key appeared in with its stats,
+    /// as `first_seen`/`last_seen` in the full output. Merging takes the earliest and
+    /// latest index as is, so partials only combine meaningfully if their record indices
+    /// were counted in the same space (e.g. one stream split by ranges, not separate files).
+    pub track_seen_range: bool,
+    /// Leave out keys that were only ever objects or arrays when producing output.
+    pub leaves_only: bool,
+    /// Backslash-escape `\`, the separator and the characters of the array marker
+    /// inside object member names so a member called `a[b]` can't be confused with
+    /// array paths. Group key paths are then parsed with the same escapes.
+    pub escape_keys: bool,
+    /// Lowercase object member names before building key paths, so `userId` and
+    /// `userid` share one entry. Lossy: the output only shows the lowercased name.
+    pub lowercase_keys: bool,
+    /// Rewrite every object member name before it goes into a key path (applied before
+    /// `lowercase_keys`); names it maps to the same string share one entry, and only
+    /// that form appears in the output. Partials that get merged must use the same one.
+    pub key_normalizer: Option<fn(&str) -> String>,
+    /// Placed between a path and an object member name. Defaults to `.`; group key
+    /// paths are split on it too.
+    pub separator: Option<String>,
+    /// Appended to a path for the elements of an array. Defaults to `[]`.
+    pub array_marker: Option<String>,
+    /// Key the first N elements of every array by position (`key[0]`, `key[1]`, ...)
+    /// instead of folding them all into the array marker; later elements still fold.
+    pub array_index_limit: Option<usize>,
+    /// Stop descending this many levels below the root: containers at that depth are
+    /// counted as objects/arrays but their children aren't visited. `Some(0)` records
+    /// only the root.
+    pub max_depth: Option<usize>,
+    /// Arrays whose key matches one of these globs (same paths as `key_filter`, e.g.
+    /// `line_items` or `orders[].tags`) only get their length stats; their elements
+    /// aren't visited, like containers cut off by `max_depth`.
+    pub collapse_arrays: Option<Vec<String>>,
+    /// Let [`JsonStat::type_conflicts`] treat int and float as a single `number` type.
+    pub unify_numbers: bool,
+    /// Top-level member holding how many records each record stands for, for
+    /// pre-aggregated input: every count (record count included) is multiplied by it,
+    /// min/max aren't affected. The member itself isn't recorded. Records without it, or
+    /// whose weight isn't a non-negative integer, count once; a weight of 0 skips them.
+    /// Weights above `u32::MAX` are capped to it.
+    pub weight_field: Option<String>,
+    /// Reject records that aren't JSON objects: `stat_value` returns false for them and
+    /// `stat_str` counts their line as failed.
+    pub objects_only: bool,
+    /// Add a `pct` to each type block of the summary output: that type's share of the
+    /// key's values, rounded to 4 decimals.
+    pub type_percentages: bool,
+    /// Report ints and floats together as one `number` block over f64, in place of the
+    /// separate `int` and `float` blocks of the summary output and `Display`.
+    pub number_bucket: bool,
+    /// Emit every type block in the summary output and `Display`, with a count of zero
+    /// for types a key never held, so each key has the same shape from run to run.
+    /// With `number_bucket` the `number` block stands in for `int` and `float`; without
+    /// it mixed keys don't get the combined `number` block either.
+    pub verbose: bool,
+    /// Round float min/max and int/float means to this many decimal places in the
+    /// summary output and `Display`; the stats themselves (and so merges) keep full
+    /// precision.
+    pub float_precision: Option<u32>,
+    /// Keep the JSON value that set each key's int/float min and max, written out as
+    /// `min_example`/`max_example` in the full (`to_json_str(true)`) output, redacted
+    /// like every other kept value under `redact_values`.
+    pub track_extreme_examples: bool,
+    /// Add an `absent` count to each member key in the summary output: how many of its
+    /// parent objects didn't have the member, which `null` doesn't cover. See
+    /// [`JsonStat::absent_counts`].
+    pub count_absent: bool,
+    /// Key the record root by this name instead of the empty string, so stats from
+    /// different sources keep apart when merged (`users.name` vs `orders.name`). With a
+    /// group key the group value follows it after the separator, `<missing>` standing
+    /// in for an empty one.
+    pub root_prefix: Option<String>,
+    /// Joins the values of a multi-path group key. Defaults to `|`.
+    pub group_separator: Option<String>,
+    /// Only record keys whose path below the group root (without the leading separator,
+    /// e.g. `user.name` or `events[]`) matches one of these globs; `*` matches any run
+    /// of characters and `?` any one. Branches no pattern can match aren't visited.
+    pub key_filter: Option<Vec<String>>,
+    /// Keys that aren't profiled at all: they and everything below them get no entry and
+    /// their values are never looked at. Globs over the path below the group root, as
+    /// in `key_filter`; `*password` also matches `user.password`. While this or
+    /// `mask_keys` is set, `samples` only keeps scalars.
+    pub deny_keys: Option<Vec<String>>,
+    /// Keys whose entries keep only how often each type was seen, without any min/max,
+    /// top values, samples or other value-derived state. Same globs as `deny_keys`;
+    /// children aren't included unless a pattern matches them too.
+    pub mask_keys: Option<Vec<String>>,
+    /// Track at most this many distinct keys; values for keys beyond it are handled as
+    /// `key_overflow` says and counted in [`JsonStat::overflow_count`].
+    pub key_limit: Option<usize>,
+    /// Soft cap on distinct keys for bounding memory: the record that goes past it is
+    /// still stat'ed in full, after which [`JsonStat::max_keys_exceeded`] is set and
+    /// `stat_value`/`stat_str` refuse every further record (returning false / an error);
+    /// `stat_reader` just stops reading.
+    pub max_keys: Option<usize>,
+    /// See [`KeyOverflow`].
+    pub key_overflow: KeyOverflow,
+    /// Count floats without a fractional part, such as `1.0`, as ints.
+    pub integral_floats_as_int: bool,
+    /// Count strings longer than this many bytes as `oversize`, to spot fields that
+    /// sometimes carry embedded blobs.
+    pub oversize_string_len: Option<usize>,
+    /// Count strings that parse as an int or a finite float, such as `"42"` or `"3.14"`,
+    /// as `numeric_like`.
+    pub detect_numeric_strings: bool,
+    /// Also track string lengths in chars; the plain string min/max are in bytes.
+    pub string_char_lengths: bool,
+    /// Bucket every int/float value per key; see [`HistogramConfig`].
+    pub histogram: Option<HistogramConfig>,
+    /// Estimate p50/p95/p99 of every int/float key with a t-digest of this compression.
+    /// Around 100 is typical; per-key memory grows linearly with it (~10 KiB at 100).
+    pub quantiles: Option<usize>,
+    /// Only emit these metrics (e.g. `count`, `min`, `max`) inside each type block of
+    /// the summary output. `None` emits everything.
+    pub metrics: Option<Vec<String>>,
+    /// Group keys to keep extra grouped views for, all filled from the same pass and
+    /// read back with [`JsonStat::view`].
+    pub views: Vec<String>,
+    /// Count how deeply each array at a path nests further arrays (`[1]` is 1, `[[1]]` is 2).
+    pub array_depth_histogram: bool,
+    /// Count, per group, each distinct set of top-level keys a record had.
+    pub track_shapes: bool,
+    /// Recognise ISO-8601 date/datetime strings and track the earliest and latest seen.
+    pub detect_datetime: bool,
+    /// Count strings that look like a date, datetime, UUID, email or URL, and name the
+    /// format that covers at least 90% of a key's strings.
+    pub detect_formats: bool,
+    /// Named regexes every string value is tested against, counting matches per key.
+    /// See [`JsonStat::add_classifier`].
+    #[cfg(feature = "regex")]
+    pub classifiers: Vec<(String, regex::Regex)>,
+    /// Hash used by value-based features such as distinct counting. Defaults to
+    /// [`canonical_hash`] over 64-bit FNV-1a, which is the same on every build and
+    /// platform; all partials that get merged together must use the same function.
+    pub value_hasher: Option<fn(&Value) -> u64>,
+    /// Time `stat_str` calls so [`JsonStat::throughput`] can report rates.
+    pub track_throughput: bool,
+    /// Count records from `stat_str` that are not strict JSON. serde_json already rejects
+    /// NaN/Infinity, so what remains to catch is objects with duplicate keys.
+    pub strict: bool,
+    /// Thresholds used by [`JsonStat::drift_against`].
+    pub drift: DriftThresholds,
+    /// Only record values of these types. Containers are still walked to reach their
+    /// children, but keys that never held a recorded type get no entry.
+    pub record_types: Option<Vec<ValueType>>,
+    /// Remember, per key, the index of the first record whose type differed from the
+    /// key's first type. Order dependent, like `track_last_seen`.
+    pub track_type_changes: bool,
+    /// Replace every raw value the stats would otherwise keep (timestamps, samples and
+    /// the like) with a hash as it is captured. Counts and lengths are unaffected, but
+    /// redacted timestamps no longer order chronologically.
+    pub redact_values: bool,
+    /// Estimate how many distinct values each key held, using a HyperLogLog sketch of
+    /// 1 KiB per key with roughly 3.25% standard error. Values are hashed with
+    /// `value_hasher`.
+    pub distinct: bool,
+    /// Keep the N most frequent string/int/bool values per key.
+    pub top_n: Option<usize>,
+    /// Strings longer than this many bytes are left out of the top-N tracking.
+    /// Defaults to 256.
+    pub top_value_max_len: Option<usize>,
+    /// Keep a few example values per key, picked evenly among the distinct values seen.
+    /// Long strings and containers are shortened.
+    pub samples: bool,
+    /// How many examples `samples` keeps per key. Defaults to 3.
+    pub sample_size: Option<usize>,
+    /// Only stat the 1st, N+1th, 2N+1th, ... record handed to `stat_value` and pass over
+    /// the rest. Counts stay raw; summary output reports the rate as `<sample_every>`
+    /// and [`JsonStat::skipped_record_count`] how many records were passed over.
+    pub sample_every: Option<usize>,
+}
+impl JsonStatConfig {
+    pub fn hash_value(&self, value: &Value) -> u64 {
+        match self.value_hasher {
+            Some(f) => f(value),
+            None => hash::default_value_hash(value),
         }
     }
-    pub fn stat_str(&mut self, line: &str) -> bool {
-        if let Ok(value) = serde_json::from_str(line) {
-            self.stat_value(&value)
+    fn top_value_max_len(&self) -> usize {
+        self.top_value_max_len.unwrap_or(DEFAULT_TOP_VALUE_MAX_LEN)
+    }
+    fn sample_size(&self) -> usize {
+        self.sample_size.unwrap_or(DEFAULT_SAMPLE_SIZE)
+    }
+    fn retain_value(&self, value: &Value) -> Value {
+        if self.redact_values {
+            Value::String(format!("<redacted:{:016x}>", self.hash_value(value)))
         } else {
-            false
+            value.clone()
         }
     }
-    fn get_group_key(&self, value: &Value) -> String {
-        if let Some(key) = &self.group_key {
-            let mut val = value;
-            for k in key.split('.') {
-                val = match val.as_object() {
-                    Some(v) => match v.get(k) {
-                        Some(_v) => _v,
-                        None => {
-                            return "".to_string();
-                        }
-                    },
-                    None => {
-                        return "".to_string();
-                    }
-                }
-            }
-            if let Some(r) = val.as_str() {
-                r.to_string()
-            } else {
-                "".to_string()
-            }
+    fn retain_str(&self, s: &str) -> String {
+        if self.redact_values {
+            format!(
+                "<redacted:{:016x}>",
+                self.hash_value(&Value::String(s.to_string()))
+            )
         } else {
-            "".to_string()
+            s.to_string()
         }
     }
-    pub fn stat_value(&mut self, value: &Value) -> bool {
-        let mut todo_list = Vec::new();
-        todo_list.push((self.get_group_key(value), value.clone()));
-        while let Some((k, v)) = todo_list.pop() {
-            let (item, list) = self.stat_key_value(&k, &v);
-            if !list.is_empty() {
-                todo_list.extend(list);
+    fn separator(&self) -> &str {
+        self.separator
+            .as_deref()
+            .filter(|s| !s.is_empty())
+            .unwrap_or(".")
+    }
+    fn group_separator(&self) -> &str {
+        self.group_separator.as_deref().unwrap_or("|")
+    }
+    fn array_marker(&self) -> &str {
+        self.array_marker.as_deref().unwrap_or("[]")
+    }
+    fn normalize_member<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        let name = match self.key_normalizer {
+            Some(f) => Cow::Owned(f(name)),
+            None => Cow::Borrowed(name),
+        };
+        if self.lowercase_keys && name.chars().any(char::is_uppercase) {
+            Cow::Owned(name.to_lowercase())
+        } else {
+            name
+        }
+    }
+    fn member_key(&self, parent: &str, name: &str) -> String {
+        let name = self.normalize_member(name);
+        let name = name.as_ref();
+        let separator = self.separator();
+        let key = if self.escape_keys {
+            let array_marker = self.array_marker();
+            let mut key = String::with_capacity(parent.len() + name.len() + separator.len());
+            key.push_str(parent);
+            key.push_str(separator);
+            for c in name.chars() {
+                if c == '\\' || separator.contains(c) || array_marker.contains(c) {
+                    key.push('\\');
+                }
+                key.push(c);
             }
-            if let Some(v) = self.items.get_mut(&k) {
-                v.merge(&item);
+            key
+        } else {
+            format!("{}{}{}", parent, separator, name)
+        };
+        self.limit_key(key)
+    }
+    fn element_key(&self, parent: &str) -> String {
+        self.limit_key(format!("{}{}", parent, self.array_marker()))
+    }
+    fn indexed_element_key(&self, parent: &str, index: usize) -> String {
+        self.limit_key(format!("{}[{}]", parent, index))
+    }
+    fn split_path(&self, path: &str) -> Vec<String> {
+        let separator = self.separator();
+        if !self.escape_keys {
+            return path.split(separator).map(|k| k.to_string()).collect();
+        }
+        let mut ret = vec![String::new()];
+        let mut rest = path;
+        while let Some(c) = rest.chars().next() {
+            if c == '\\' {
+                rest = &rest[1..];
+                if let Some(c) = rest.chars().next() {
+                    ret.last_mut().unwrap().push(c);
+                    rest = &rest[c.len_utf8()..];
+                }
+            } else if let Some(r) = rest.strip_prefix(separator) {
+                ret.push(String::new());
+                rest = r;
             } else {
-                self.items.insert(k, item);
+                ret.last_mut().unwrap().push(c);
+                rest = &rest[c.len_utf8()..];
             }
         }
-        true
+        ret
+    }
+    // The path a member or element key was built from, and whether it was a member;
+    // `None` when the key has no separator or array suffix left to strip.
+    fn parent_key<'a>(&self, key: &'a str) -> Option<(&'a str, bool)> {
+        let separator = self.separator();
+        let array_marker = self.array_marker();
+        let mut ret = None;
+        let mut chars = key.char_indices();
+        while let Some((i, c)) = chars.next() {
+            let rest = &key[i..];
+            if c == '\\' && self.escape_keys {
+                chars.next();
+            } else if rest.starts_with(separator) {
+                ret = Some((&key[..i], true));
+            } else if (!array_marker.is_empty() && rest == array_marker)
+                || (self.array_index_limit.is_some() && is_index_suffix(rest))
+            {
+                ret = Some((&key[..i], false));
+            }
+        }
+        ret
+    }
+    // (record, descend) for `key` below `root`: whether one of the patterns matches it,
+    // and whether one could still match it or a path below it. Roots always pass.
+    fn match_key(&self, patterns: &[String], root: &str, key: &str) -> (bool, bool) {
+        let rel = match key.strip_prefix(root) {
+            Some(rel) if !rel.is_empty() => rel.strip_prefix(self.separator()).unwrap_or(rel),
+            _ => return (true, true),
+        };
+        (
+            patterns.iter().any(|p| glob::glob_match(p, rel)),
+            patterns.iter().any(|p| glob::glob_prefix_match(p, rel)),
+        )
+    }
+    fn filter_key(&self, root: &str, key: &str) -> (bool, bool) {
+        if let Some(patterns) = &self.deny_keys {
+            if key != root && self.match_key(patterns, root, key).0 {
+                return (false, false);
+            }
+        }
+        match &self.key_filter {
+            Some(patterns) => self.match_key(patterns, root, key),
+            None => (true, true),
+        }
+    }
+    fn masks_key(&self, root: &str, key: &str) -> bool {
+        match &self.mask_keys {
+            Some(patterns) => key != root && self.match_key(patterns, root, key).0,
+            None => false,
+        }
+    }
+    fn round_float(&self, x: f64) -> f64 {
+        match self.float_precision {
+            Some(places) => {
+                let scale = 10f64.powi(places as i32);
+                // scaling huge values overflows, and they have no decimals left to drop
+                Some((x * scale).round() / scale)
+                    .filter(|r| r.is_finite())
+                    .unwrap_or(x)
+            }
+            None => x,
+        }
+    }
+    fn collapses_array(&self, root: &str, key: &str) -> bool {
+        match &self.collapse_arrays {
+            Some(patterns) => key != root && self.match_key(patterns, root, key).0,
+            None => false,
+        }
+    }
+    fn limit_key(&self, mut key: String) -> String {
+        if let Some(max) = self.max_key_length {
+            if key.len() > max {
+                let mut end = max;
+                while !key.is_char_boundary(end) {
+                    end -= 1;
+                }
+                key.truncate(end);
+                key.push_str(TRUNCATED_KEY_MARKER);
+            }
+        }
+        key
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct RootShapeCounts {
+    pub object: usize,
+    pub array: usize,
+    pub scalar: usize,
+}
+impl RootShapeCounts {
+    fn add(&mut self, value: &Value, weight: usize) {
+        match value {
+            Value::Object(_) => self.object += weight,
+            Value::Array(_) => self.array += weight,
+            _ => self.scalar += weight,
+        }
+    }
+    fn merge(&mut self, other: &Self) {
+        self.object += other.object;
+        self.array += other.array;
+        self.scalar += other.scalar;
+    }
+}
+
+#[derive(Clone)]
+struct TypeChangeTracker {
+    initial: &'static str,
+    first_seen: usize,
+    changed_at: Option<usize>,
+}
+
+#[derive(Clone)]
+pub struct JsonStat {
+    items: BTreeMap<String, JsonStatItem>,
+    group_keys: Vec<String>,
+    config: JsonStatConfig,
+    record_count: usize,
+    last_seen: BTreeMap<String, usize>,
+    root_shapes: RootShapeCounts,
+    views: BTreeMap<String, JsonStat>,
+    shapes: BTreeMap<String, BTreeMap<Vec<String>, usize>>,
+    byte_count: usize,
+    elapsed: Duration,
+    strict_violations: usize,
+    max_record_depth: usize,
+    max_branch_depths: BTreeMap<String, usize>,
+    max_record_width: usize,
+    type_changes: BTreeMap<String, TypeChangeTracker>,
+    line_count: usize,
+    failed_line_count: usize,
+    overflow_count: usize,
+    skipped_record_count: usize,
+    // records that got as far as `sample_every`, each once whatever its weight
+    sampled_record_count: usize,
+    // weight of the record being stat'ed, see `JsonStatConfig::weight_field`
+    record_weight: usize,
+    max_keys_exceeded: bool,
+}
+impl JsonStat {
+    pub fn new() -> Self {
+        Self::new_with_config(JsonStatConfig::default())
+    }
+    pub fn new_by_group(group_key: &str) -> Self {
+        Self::new_by_group_with_config(group_key, JsonStatConfig::default())
+    }
+    pub fn new_with_config(config: JsonStatConfig) -> Self {
+        // views only see the records the parent kept, so they don't sample again
+        let view_config = JsonStatConfig {
+            views: Vec::new(),
+            sample_every: None,
+            ..config.clone()
+        };
+        let views = config
+            .views
+            .iter()
+            .map(|k| {
+                (
+                    k.clone(),
+                    Self::new_by_group_with_config(k, view_config.clone()),
+                )
+            })
+            .collect();
+        JsonStat {
+            items: BTreeMap::new(),
+            group_keys: Vec::new(),
+            config,
+            record_count: 0,
+            last_seen: BTreeMap::new(),
+            root_shapes: RootShapeCounts::default(),
+            views,
+            shapes: BTreeMap::new(),
+            byte_count: 0,
+            elapsed: Duration::ZERO,
+            strict_violations: 0,
+            line_count: 0,
+            failed_line_count: 0,
+            overflow_count: 0,
+            skipped_record_count: 0,
+            sampled_record_count: 0,
+            record_weight: 1,
+            max_keys_exceeded: false,
+            max_record_depth: 0,
+            max_branch_depths: BTreeMap::new(),
+            max_record_width: 0,
+            type_changes: BTreeMap::new(),
+        }
+    }
+    pub fn new_with_topn(n: usize) -> Self {
+        Self::new_with_config(JsonStatConfig {
+            top_n: Some(n),
+            ..Default::default()
+        })
+    }
+    pub fn new_by_group_with_config(group_key: &str, config: JsonStatConfig) -> Self {
+        Self::new_by_groups_with_config(&[group_key], config)
+    }
+    /// Groups by several paths at once; the group label joins their values with
+    /// `group_separator`, using `<missing>` for a path a record doesn't have.
+    pub fn new_by_groups(group_keys: &[&str]) -> Self {
+        Self::new_by_groups_with_config(group_keys, JsonStatConfig::default())
+    }
+    pub fn new_by_groups_with_config(group_keys: &[&str], config: JsonStatConfig) -> Self {
+        JsonStat {
+            group_keys: group_keys.iter().map(|k| k.to_string()).collect(),
+            ..Self::new_with_config(config)
+        }
+    }
+    /// distinct key paths tracked, not counting the `key_overflow` aggregate
+    pub fn key_count(&self) -> usize {
+        self.items.len() - self.items.contains_key(OVERFLOW_KEY) as usize
+    }
+    /// values of each type recorded across every key, containers included
+    pub fn type_totals(&self) -> BTreeMap<&'static str, usize> {
+        let mut ret = BTreeMap::new();
+        for item in self.items.values() {
+            for (name, count) in item.type_counts() {
+                *ret.entry(name).or_insert(0) += count;
+            }
+        }
+        ret
+    }
+    pub fn record_count(&self) -> usize {
+        self.record_count
+    }
+    /// records passed over by `JsonStatConfig::sample_every`
+    pub fn skipped_record_count(&self) -> usize {
+        self.skipped_record_count
+    }
+    pub fn last_seen_index(&self) -> &BTreeMap<String, usize> {
+        &self.last_seen
+    }
+    pub fn type_change_points(&self) -> BTreeMap<String, usize> {
+        self.type_changes
+            .iter()
+            .filter_map(|(k, t)| t.changed_at.map(|i| (k.clone(), i)))
+            .collect()
+    }
+    pub fn root_shape_counts(&self) -> RootShapeCounts {
+        self.root_shapes
+    }
+    pub fn view(&self, group_key: &str) -> Option<&JsonStat> {
+        self.views.get(group_key)
+    }
+    /// Counts the strings of every key matching `pattern` under `name`, from the next
+    /// value on.
+    #[cfg(feature = "regex")]
+    pub fn add_classifier(&mut self, name: &str, pattern: &str) -> Result<(), JsonStatError> {
+        let regex = regex::Regex::new(pattern)?;
+        for view in self.views.values_mut() {
+            view.config
+                .classifiers
+                .push((name.to_string(), regex.clone()));
+        }
+        self.config.classifiers.push((name.to_string(), regex));
+        Ok(())
+    }
+    pub fn shape_counts(&self) -> &BTreeMap<String, BTreeMap<Vec<String>, usize>> {
+        &self.shapes
+    }
+    pub fn stat_str(&mut self, line: &str) -> bool {
+        self.stat_str_result(line).is_ok()
+    }
+    /// Stats every line of newline-delimited JSON, returning how many were valid records.
+    /// Lines that aren't valid JSON (or UTF-8) are counted as failed and skipped; read
+    /// errors stop it. Reading also stops, without an error, once `max_keys_exceeded`.
+    pub fn stat_reader<R: BufRead>(&mut self, mut reader: R) -> std::io::Result<usize> {
+        let mut ret = 0;
+        let mut buf = Vec::new();
+        loop {
+            if self.max_keys_exceeded {
+                return Ok(ret);
+            }
+            buf.clear();
+            if reader.read_until(b'\n', &mut buf)? == 0 {
+                return Ok(ret);
+            }
+            if buf.ends_with(b"\n") {
+                buf.pop();
+                if buf.ends_with(b"\r") {
+                    buf.pop();
+                }
+            }
+            match std::str::from_utf8(&buf) {
+                Ok(line) => {
+                    if self.stat_str(line) {
+                        ret += 1;
+                    }
+                }
+                Err(_) => {
+                    self.line_count += 1;
+                    self.failed_line_count += 1;
+                }
+            }
+        }
+    }
+    /// A new stat with `config`, filled from every line of a JSONL file as `stat_reader`
+    /// does; only I/O errors are returned, bad lines are counted.
+    pub fn from_jsonl_file<P: AsRef<Path>>(
+        path: P,
+        config: JsonStatConfig,
+    ) -> std::io::Result<JsonStat> {
+        Self::from_jsonl_reader(BufReader::new(File::open(path)?), config)
+    }
+    /// like `from_jsonl_file`, for any buffered reader such as stdin or a decompressor
+    pub fn from_jsonl_reader<R: BufRead>(
+        reader: R,
+        config: JsonStatConfig,
+    ) -> std::io::Result<JsonStat> {
+        let mut ret = Self::new_with_config(config);
+        ret.stat_reader(reader)?;
+        Ok(ret)
+    }
+    /// like `stat_str`, but hands back why a line couldn't be parsed
+    pub fn stat_str_result(&mut self, line: &str) -> Result<(), serde_json::Error> {
+        if self.max_keys_exceeded {
+            return Err(serde::de::Error::custom(MAX_KEYS_EXCEEDED));
+        }
+        let start = self.config.track_throughput.then(Instant::now);
+        self.byte_count += line.len();
+        self.line_count += 1;
+        let ret = match serde_json::from_str(line) {
+            Ok(value) => {
+                if self.config.strict
+                    && matches!(
+                        serde_json::from_str(line),
+                        Ok(strict::HasDuplicateKeys(true))
+                    )
+                {
+                    self.strict_violations += 1;
+                }
+                if self.stat_value(&value) {
+                    Ok(())
+                } else {
+                    self.failed_line_count += 1;
+                    Err(serde::de::Error::custom("record is not an object"))
+                }
+            }
+            Err(e) => {
+                self.failed_line_count += 1;
+                Err(e)
+            }
+        };
+        if let Some(start) = start {
+            self.elapsed += start.elapsed();
+        }
+        ret
+    }
+    /// deepest container nesting in any single record: `{}` is 1, `{"a":[1]}` is 2
+    pub fn max_record_depth(&self) -> usize {
+        self.max_record_depth
+    }
+    /// Deepest container nesting below each member or element of the record root, keyed
+    /// by that first-level key: `{"a":{"b":[1]}}` gives `.a` a depth of 2. Branches that
+    /// never held a container are left out.
+    pub fn max_branch_depths(&self) -> &BTreeMap<String, usize> {
+        &self.max_branch_depths
+    }
+    /// most members any single object has had
+    pub fn max_record_width(&self) -> usize {
+        self.max_record_width
+    }
+    pub fn strict_violations(&self) -> usize {
+        self.strict_violations
+    }
+    /// lines passed to `stat_str`, and how many of those weren't valid JSON
+    pub fn line_count(&self) -> usize {
+        self.line_count
+    }
+    pub fn failed_line_count(&self) -> usize {
+        self.failed_line_count
+    }
+    /// whether `JsonStatConfig::max_keys` was gone past; no more records are taken then
+    pub fn max_keys_exceeded(&self) -> bool {
+        self.max_keys_exceeded
+    }
+    /// values that didn't get their own key because `key_limit` was reached
+    pub fn overflow_count(&self) -> usize {
+        self.overflow_count
+    }
+    pub fn byte_count(&self) -> usize {
+        self.byte_count
+    }
+    /// (records/sec, bytes/sec) over the time spent in `stat_str`; merged partials add
+    /// their durations, so this is per-thread throughput rather than wall-clock
+    pub fn throughput(&self) -> (f64, f64) {
+        let secs = self.elapsed.as_secs_f64();
+        if secs == 0.0 {
+            return (0.0, 0.0);
+        }
+        (
+            self.record_count as f64 / secs,
+            self.byte_count as f64 / secs,
+        )
+    }
+    // the group key path's value in a record, if it is a scalar; null gets a sentinel
+    // so it stays apart from records that lack the path
+    fn group_value(&self, value: &Value, path: &str) -> Option<String> {
+        let mut val = value;
+        for k in self.config.split_path(path) {
+            val = val.as_object()?.get(&k)?;
+        }
+        match val {
+            Value::String(s) => Some(s.clone()),
+            Value::Number(_) | Value::Bool(_) => Some(val.to_string()),
+            Value::Null => Some(NULL_GROUP_VALUE.to_string()),
+            _ => None,
+        }
+    }
+    fn get_group_key(&self, value: &Value) -> String {
+        let group = self.get_group_value(value);
+        match self.config.root_prefix.as_deref() {
+            Some(prefix) if self.group_keys.is_empty() => prefix.to_string(),
+            // an empty group would leave the record root looking like the parent of
+            // every other group's root
+            Some(prefix) if group.is_empty() => {
+                format!(
+                    "{}{}{}",
+                    prefix,
+                    self.config.separator(),
+                    MISSING_GROUP_VALUE
+                )
+            }
+            Some(prefix) => format!("{}{}{}", prefix, self.config.separator(), group),
+            None => group,
+        }
+    }
+    fn get_group_value(&self, value: &Value) -> String {
+        match self.group_keys.as_slice() {
+            [] => String::new(),
+            [key] => self.group_value(value, key).unwrap_or_default(),
+            keys => keys
+                .iter()
+                .map(|k| {
+                    self.group_value(value, k)
+                        .unwrap_or_else(|| MISSING_GROUP_VALUE.to_string())
+                })
+                .collect::<Vec<_>>()
+                .join(self.config.group_separator()),
+        }
+    }
+    /// A scalar record is counted under the root key with no children, and isn't
+    /// grouped: its group value is missing. Returns false for records rejected by
+    /// `objects_only`.
+    pub fn stat_value(&mut self, value: &Value) -> bool {
+        self.stat_value_with(value, &mut |_, _| {})
+    }
+    /// Like `stat_value`, also calling `visitor` with the key and value of every leaf
+    /// recorded on the way: scalars, and containers cut off by `max_depth` or
+    /// `collapse_arrays`.
+    pub fn stat_value_with(
+        &mut self,
+        value: &Value,
+        visitor: &mut impl FnMut(&str, &Value),
+    ) -> bool {
+        if self.max_keys_exceeded {
+            return false;
+        }
+        if self.config.objects_only && !value.is_object() {
+            return false;
+        }
+        // views read the weight themselves
+        let record = value;
+        let unweighted;
+        let value = match (&self.config.weight_field, value) {
+            (Some(field), Value::Object(obj)) if obj.contains_key(field) => {
+                let mut obj = obj.clone();
+                self.record_weight = obj
+                    .remove(field)
+                    .and_then(|w| w.as_u64())
+                    .unwrap_or(1)
+                    .min(MAX_RECORD_WEIGHT) as usize;
+                unweighted = Value::Object(obj);
+                &unweighted
+            }
+            _ => {
+                self.record_weight = 1;
+                value
+            }
+        };
+        if self.record_weight == 0 {
+            return true;
+        }
+        let index = self.sampled_record_count;
+        self.sampled_record_count += 1;
+        if let Some(n) = self.config.sample_every.filter(|n| *n > 1) {
+            if !index.is_multiple_of(n) {
+                self.skipped_record_count += 1;
+                return true;
+            }
+        }
+        self.root_shapes.add(value, self.record_weight);
+        let root = self.config.limit_key(self.get_group_key(value));
+        if let (true, Value::Object(obj)) = (self.config.track_shapes, value) {
+            let mut shape: Vec<String> = obj.keys().cloned().collect();
+            shape.sort();
+            *self
+                .shapes
+                .entry(root.clone())
+                .or_default()
+                .entry(shape)
+                .or_insert(0) += self.record_weight;
+        }
+        match value {
+            Value::Object(obj)
+                if self.config.max_depth != Some(0)
+                    && obj.values().all(|v| !v.is_object() && !v.is_array()) =>
+            {
+                self.stat_flat_object(root, value, obj, visitor);
+            }
+            _ => self.stat_tree(root, value, visitor),
+        }
+        self.record_count += self.record_weight;
+        for view in self.views.values_mut() {
+            view.stat_value(record);
+        }
+        true
+    }
+    /// `stat_value` on each value in turn, returning how many of them were stat'ed
+    pub fn stat_values<I: IntoIterator<Item = Value>>(&mut self, iter: I) -> usize {
+        iter.into_iter().filter(|v| self.stat_value(v)).count()
+    }
+    /// Stats each element of a top-level array as its own record, rather than the array
+    /// as one record. Returns whether every element was stat'ed, as `stat_value` does
+    /// for one record; false, without stat'ing anything, for non-arrays.
+    pub fn stat_array(&mut self, value: &Value) -> bool {
+        match value {
+            Value::Array(arr) => arr.iter().filter(|v| self.stat_value(v)).count() == arr.len(),
+            _ => false,
+        }
+    }
+    // the general path: walks `value` depth first, recording keys in document order
+    fn stat_tree(&mut self, root: String, value: &Value, visitor: &mut impl FnMut(&str, &Value)) {
+        // first-level keys seen in this record and the depth reached below each;
+        // todo entries carry the index of the branch they belong to, and whether they
+        // are the first member of their object to land on their key
+        let mut branches: Vec<(String, usize)> = Vec::new();
+        let mut todo_list = Vec::new();
+        todo_list.push((root.clone(), value.clone(), 0, 0, false));
+        while let Some((k, v, depth, mut branch, member)) = todo_list.pop() {
+            if depth == 1 && (v.is_object() || v.is_array()) {
+                branch = branches.len();
+                branches.push((k.clone(), 0));
+            }
+            match &v {
+                Value::Object(obj) => {
+                    self.max_record_depth = self.max_record_depth.max(depth + 1);
+                    self.max_record_width = self.max_record_width.max(obj.len());
+                }
+                Value::Array(_) => {
+                    self.max_record_depth = self.max_record_depth.max(depth + 1);
+                }
+                _ => {}
+            }
+            if depth > 0 && (v.is_object() || v.is_array()) {
+                branches[branch].1 = branches[branch].1.max(depth);
+            }
+            let (record, descend) = self.config.filter_key(&root, &k);
+            if !descend {
+                continue;
+            }
+            if self.config.max_depth.is_some_and(|max| depth >= max)
+                || (v.is_array() && self.config.collapses_array(&root, &k))
+            {
+                if record {
+                    visitor(&k, &v);
+                    let mut item = JsonStatItem::new();
+                    item.add(&v, &self.config);
+                    item.member_count = member as usize;
+                    self.record_value_item(&root, k, item);
+                }
+                continue;
+            }
+            let (mut item, list) = self.stat_key_value(&k, &v);
+            item.member_count = member as usize;
+            if !list.is_empty() {
+                let mut members = BTreeSet::new();
+                let list: Vec<_> = list
+                    .into_iter()
+                    .map(|(k, child)| {
+                        let first = v.is_object() && members.insert(k.clone());
+                        (k, child, depth + 1, branch, first)
+                    })
+                    .collect();
+                // reversed so they pop in document order, which `key_limit` and
+                // `stat_flat_object` depend on
+                todo_list.extend(list.into_iter().rev());
+            }
+            if record {
+                if !v.is_object() && !v.is_array() {
+                    visitor(&k, &v);
+                }
+                self.record_value_item(&root, k, item);
+            }
+        }
+        for (k, depth) in branches {
+            let d = self.max_branch_depths.entry(k).or_insert(0);
+            *d = (*d).max(depth);
+        }
+    }
+    // flat objects of scalars are the common case, so skip cloning them onto the todo list;
+    // records the root and then its members in order, like the general path does
+    fn stat_flat_object(
+        &mut self,
+        root: String,
+        value: &Value,
+        obj: &Map<String, Value>,
+        visitor: &mut impl FnMut(&str, &Value),
+    ) {
+        self.max_record_depth = self.max_record_depth.max(1);
+        self.max_record_width = self.max_record_width.max(obj.len());
+        let mut item = JsonStatItem::new();
+        item.add(value, &self.config);
+        self.record_item(root.clone(), item);
+        let mut members = BTreeSet::new();
+        for (k, v) in obj {
+            let key = self.config.member_key(&root, k);
+            if !self.config.filter_key(&root, &key).0 {
+                continue;
+            }
+            visitor(&key, v);
+            let mut item = JsonStatItem::new();
+            item.add(v, &self.config);
+            item.member_count = members.insert(key.clone()) as usize;
+            self.record_value_item(&root, key, item);
+        }
+    }
+    fn accepts_key(&self, key: &str) -> bool {
+        match self.config.key_limit {
+            Some(max) if key != OVERFLOW_KEY && !self.items.contains_key(key) => {
+                self.items.len() - (self.items.contains_key(OVERFLOW_KEY) as usize) < max
+            }
+            _ => true,
+        }
+    }
+    fn overflow(&mut self, item: &JsonStatItem) {
+        self.overflow_count += item.total_count();
+        if self.config.key_overflow == KeyOverflow::Aggregate {
+            self.items
+                .entry(OVERFLOW_KEY.to_string())
+                .or_insert_with(JsonStatItem::new)
+                .merge(item);
+        }
+    }
+    fn record_value_item(&mut self, root: &str, key: String, mut item: JsonStatItem) {
+        if self.config.masks_key(root, &key) {
+            item.mask();
+        }
+        self.record_item(key, item);
+    }
+    fn record_item(&mut self, key: String, mut item: JsonStatItem) {
+        if item.total_count() == 0 {
+            return;
+        }
+        if self.record_weight > 1 {
+            item.scale(self.record_weight);
+        }
+        if !self.accepts_key(&key) {
+            self.overflow(&item);
+            return;
+        }
+        if self.config.track_last_seen {
+            self.last_seen.insert(key.clone(), self.record_count);
+        }
+        if self.config.track_seen_range {
+            item.first_seen = Some(self.record_count);
+            item.last_seen = Some(self.record_count);
+        }
+        if self.config.track_type_changes {
+            // a freshly built item holds exactly the one value it was built from
+            let type_name = item.dominant_type();
+            let record = self.record_count;
+            let tracker = self
+                .type_changes
+                .entry(key.clone())
+                .or_insert(TypeChangeTracker {
+                    initial: type_name,
+                    first_seen: record,
+                    changed_at: None,
+                });
+            if tracker.changed_at.is_none() && tracker.initial != type_name {
+                tracker.changed_at = Some(record);
+            }
+        }
+        if let Some(v) = self.items.get_mut(&key) {
+            v.merge(&item);
+        } else {
+            if self
+                .config
+                .max_keys
+                .is_some_and(|max| self.items.len() >= max)
+            {
+                self.max_keys_exceeded = true;
+            }
+            self.items.insert(key, item);
+        }
     }
     fn stat_key_value(&self, key: &str, value: &Value) -> (JsonStatItem, Vec<(String, Value)>) {
         let mut item = JsonStatItem::new();
-        let ret = item.stat(key, value);
+        let ret = item.stat(key, value, &self.config);
         (item, ret)
     }
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &JsonStatItem)> {
+        self.items.iter().map(|(k, v)| (k.as_str(), v))
+    }
+    pub fn get(&self, key: &str) -> Option<&JsonStatItem> {
+        self.items.get(key)
+    }
+    /// Drops everything collected so far, keeping the group key and config.
+    pub fn clear(&mut self) {
+        let group_keys = std::mem::take(&mut self.group_keys);
+        *self = JsonStat {
+            group_keys,
+            ..Self::new_with_config(self.config.clone())
+        };
+    }
+    pub fn is_empty(&self) -> bool {
+        self.record_count == 0 && self.items.is_empty()
+    }
+    /// Forget every min/max while keeping counts, so extremes describe only what is seen
+    /// from now on. Until a key sees a new value its min/max are left out of the output
+    /// (null in the record batch), and read as zero/empty in `summaries`.
+    pub fn reset_extremes(&mut self) {
+        for v in self.items.values_mut() {
+            v.reset_extremes();
+        }
+        for view in self.views.values_mut() {
+            view.reset_extremes();
+        }
+    }
+    /// `merge`, refusing stats grouped by other keys since their key paths don't line up
+    pub fn try_merge(&mut self, other: &Self) -> Result<(), JsonStatError> {
+        if self.group_keys != other.group_keys {
+            return Err(JsonStatError::GroupKeyMismatch(
+                self.group_keys.clone(),
+                other.group_keys.clone(),
+            ));
+        }
+        self.merge(other);
+        Ok(())
+    }
     pub fn merge(&mut self, other: &Self) {
         for (k, v) in other.items.iter() {
             if let Some(v1) = self.items.get_mut(k) {
                 v1.merge(v);
-            } else {
+            } else if self.accepts_key(k) {
                 self.items.insert(k.clone(), v.clone());
+            } else {
+                self.overflow(v);
+            }
+        }
+        // other's records are treated as coming after ours
+        for (k, index) in other.last_seen.iter() {
+            if self.items.contains_key(k) {
+                self.last_seen.insert(k.clone(), self.record_count + index);
+            }
+        }
+        for (k, t) in other
+            .type_changes
+            .iter()
+            .filter(|(k, _)| self.items.contains_key(*k))
+        {
+            let offset = self.record_count;
+            match self.type_changes.get_mut(k) {
+                Some(mine) => {
+                    if mine.changed_at.is_none() {
+                        if mine.initial != t.initial {
+                            mine.changed_at = Some(offset + t.first_seen);
+                        } else {
+                            mine.changed_at = t.changed_at.map(|i| offset + i);
+                        }
+                    }
+                }
+                None => {
+                    self.type_changes.insert(
+                        k.clone(),
+                        TypeChangeTracker {
+                            initial: t.initial,
+                            first_seen: offset + t.first_seen,
+                            changed_at: t.changed_at.map(|i| offset + i),
+                        },
+                    );
+                }
+            }
+        }
+        self.record_count += other.record_count;
+        self.root_shapes.merge(&other.root_shapes);
+        self.byte_count += other.byte_count;
+        self.elapsed += other.elapsed;
+        self.strict_violations += other.strict_violations;
+        self.line_count += other.line_count;
+        self.failed_line_count += other.failed_line_count;
+        self.overflow_count += other.overflow_count;
+        self.skipped_record_count += other.skipped_record_count;
+        self.sampled_record_count += other.sampled_record_count;
+        self.max_keys_exceeded |= other.max_keys_exceeded
+            || self
+                .config
+                .max_keys
+                .is_some_and(|max| self.items.len() > max);
+        self.max_record_depth = self.max_record_depth.max(other.max_record_depth);
+        for (k, depth) in other.max_branch_depths.iter() {
+            let d = self.max_branch_depths.entry(k.clone()).or_insert(0);
+            *d = (*d).max(*depth);
+        }
+        self.max_record_width = self.max_record_width.max(other.max_record_width);
+        for (group, shapes) in other.shapes.iter() {
+            let group = self.shapes.entry(group.clone()).or_default();
+            for (shape, count) in shapes.iter() {
+                *group.entry(shape.clone()).or_insert(0) += count;
             }
         }
+        for (k, v) in other.views.iter() {
+            if let Some(v1) = self.views.get_mut(k) {
+                v1.merge(v);
+            }
+        }
+    }
+    fn output_items(&self) -> impl Iterator<Item = (&String, &JsonStatItem)> {
+        self.items
+            .iter()
+            .filter(|(_, v)| !(self.config.leaves_only && v.is_container_only()))
+    }
+    /// `merge` with a serialized partial: the `Serialize` output as JSON or, with the
+    /// msgpack feature, `to_msgpack` output; a bare `to_json_str(true)` is taken too
+    pub fn merge_serialized(&mut self, bytes: &[u8]) -> Result<(), JsonStatError> {
+        let other = match bytes.iter().find(|c| !c.is_ascii_whitespace()) {
+            #[cfg(feature = "msgpack")]
+            Some(c) if *c != b'{' => rmp_serde::from_slice(bytes)?,
+            _ => Self::from_json_slice(bytes)?,
+        };
+        self.merge(&other);
+        Ok(())
+    }
+    /// Rebuilds a stat from its JSON `Serialize` output, or just the per-key stats from
+    /// `to_json_str(true)` output; everything not serialized (config, and for the latter
+    /// the group key and record counters) starts out at its default.
+    pub fn from_json_str(s: &str) -> Result<JsonStat, JsonStatError> {
+        Self::from_json_slice(s.as_bytes())
+    }
+    fn from_json_slice(bytes: &[u8]) -> Result<JsonStat, JsonStatError> {
+        // an items map can't pass for the struct: its entries are objects, not counters
+        serde_json::from_slice(bytes).or_else(|e| {
+            serde_json::from_slice(bytes)
+                .map(|items| JsonStat {
+                    items,
+                    ..Self::new()
+                })
+                .map_err(|_| e.into())
+        })
+    }
+    /// the `Serialize` output as msgpack, for `merge_serialized`
+    #[cfg(feature = "msgpack")]
+    pub fn to_msgpack(&self) -> Vec<u8> {
+        rmp_serde::to_vec_named(self).unwrap()
     }
     pub fn to_json_str(&self, full: bool) -> String {
-        if full {
-            serde_json::to_string(&self.items).unwrap()
-        } else {
-            let mut map = Map::new();
-            for (k, v) in self.items.iter() {
-                map.insert(k.clone(), v.to_json_value());
+        let mut buf = Vec::new();
+        self.write_json(&mut buf, full).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+    /// `to_json_str` with newlines and indentation
+    pub fn to_json_str_pretty(&self, full: bool) -> String {
+        let mut buf = Vec::new();
+        self.serialize_json(&mut serde_json::Serializer::pretty(&mut buf), full)
+            .unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+    /// `to_json_str` serialized straight into `w`, without building the string
+    pub fn write_json<W: Write>(&self, w: W, full: bool) -> std::io::Result<()> {
+        self.serialize_json(&mut serde_json::Serializer::new(w), full)
+    }
+    #[deprecated(note = "renamed to `write_json`")]
+    pub fn write_json_to<W: Write>(&self, w: W, full: bool) -> std::io::Result<()> {
+        self.write_json(w, full)
+    }
+    fn serialize_json<W: Write, F: serde_json::ser::Formatter>(
+        &self,
+        ser: &mut serde_json::Serializer<W, F>,
+        full: bool,
+    ) -> std::io::Result<()> {
+        let absent = (!full && self.config.count_absent).then(|| self.absent_counts());
+        let mut map = ser.serialize_map(None)?;
+        for (k, v) in self.output_items() {
+            if full {
+                map.serialize_entry(k, v)?;
+            } else {
+                let mut value = v.to_json_value(&self.config);
+                match absent.as_ref().and_then(|a| a.get(k)) {
+                    Some(n) if *n > 0 || self.config.verbose => {
+                        value["absent"] = json!(n);
+                    }
+                    _ => {}
+                }
+                map.serialize_entry(k, &value)?;
+            }
+        }
+        if !full {
+            map.serialize_entry(RECORD_COUNT_KEY, &self.record_count)?;
+            if let Some(n) = self.config.sample_every {
+                map.serialize_entry(SAMPLE_EVERY_KEY, &n)?;
+            }
+        }
+        map.end()?;
+        Ok(())
+    }
+    // keys that don't extend another key by a member, element or index suffix
+    fn root_keys(&self) -> Vec<&str> {
+        let separator = self.config.separator();
+        let mut roots: Vec<&str> = Vec::new();
+        for k in self.items.keys() {
+            let is_child = roots.iter().any(|r| {
+                k.len() > r.len()
+                    && k.starts_with(r)
+                    && (k[r.len()..].starts_with(separator)
+                        || k[r.len()..].starts_with(self.config.array_marker())
+                        || k[r.len()..].starts_with('['))
+            });
+            if !is_child {
+                roots.push(k);
+            }
+        }
+        roots
+    }
+    /// For each key, how often it was present relative to its parent object, or to the
+    /// record count for root keys. Array elements have no such ratio and are left out.
+    pub fn coverage(&self) -> BTreeMap<String, f64> {
+        let roots = self.root_keys();
+        let mut ret = BTreeMap::new();
+        for (k, v) in self.items.iter() {
+            let parent_count = if roots.contains(&k.as_str()) {
+                self.record_count
+            } else {
+                match self.config.parent_key(k) {
+                    Some((parent, true)) => self.items.get(parent).map_or(0, |p| p.object.count),
+                    _ => continue,
+                }
+            };
+            if parent_count > 0 {
+                ret.insert(k.clone(), v.total_count() as f64 / parent_count as f64);
+            }
+        }
+        ret
+    }
+    /// For each member key, how many of its parent objects didn't have it at all (as
+    /// opposed to having it set to null). Roots and array elements are left out, and
+    /// so are keys cut by `max_key_length`, which stand for members of several parents.
+    /// Member names that `lowercase_keys` or a `key_normalizer` fold together count
+    /// once per object. With `record_types`, a member holding an unrecorded type counts
+    /// as absent from that object.
+    pub fn absent_counts(&self) -> BTreeMap<String, usize> {
+        let roots = self.root_keys();
+        self.items
+            .iter()
+            .filter(|(k, _)| !roots.contains(&k.as_str()) && !k.ends_with(TRUNCATED_KEY_MARKER))
+            .filter_map(|(k, v)| match self.config.parent_key(k) {
+                Some((parent, true)) => {
+                    let parent_count = self.items.get(parent)?.object.count;
+                    Some((k.clone(), parent_count.saturating_sub(v.member_count)))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+    /// Share of each key's values that were null.
+    pub fn null_ratios(&self) -> BTreeMap<String, f64> {
+        self.items
+            .iter()
+            .map(|(k, v)| (k.clone(), v.null_ratio()))
+            .collect()
+    }
+    /// Every key seen as more than one type (null included), with those types in
+    /// `type_counts` order.
+    pub fn type_conflicts(&self) -> Vec<(String, Vec<&'static str>)> {
+        let mut ret = Vec::new();
+        for (k, v) in self.items.iter() {
+            let mut types = Vec::new();
+            for (name, count) in v.type_counts() {
+                if count == 0 {
+                    continue;
+                }
+                let name = match name {
+                    "int" | "float" if self.config.unify_numbers => "number",
+                    _ => name,
+                };
+                if !types.contains(&name) {
+                    types.push(name);
+                }
+            }
+            if types.len() > 1 {
+                ret.push((k.clone(), types));
             }
-            Value::Object(map).to_string()
         }
+        ret
+    }
+    /// For arrays that always had the same length N and whose positions were recorded
+    /// individually as `key[0]`..`key[N-1]` (see `array_index_limit`), the dominant type
+    /// at each position.
+    pub fn tuple_types(&self) -> BTreeMap<String, Vec<&'static str>> {
+        let mut ret = BTreeMap::new();
+        for (k, v) in self.items.iter() {
+            if v.array.count == 0 || v.array.min != v.array.max || v.array.max == 0 {
+                continue;
+            }
+            let types: Option<Vec<&'static str>> = (0..v.array.max)
+                .map(|i| {
+                    self.items
+                        .get(&self.config.indexed_element_key(k, i))
+                        .map(|v| v.dominant_type())
+                })
+                .collect();
+            if let Some(types) = types {
+                ret.insert(k.clone(), types);
+            }
+        }
+        ret
+    }
+    /// Every output key path, sorted, with its most frequent type (ties broken as in
+    /// `to_table`'s type column).
+    pub fn key_paths(&self) -> Vec<(String, String)> {
+        self.output_items()
+            .map(|(k, v)| (k.clone(), v.dominant_type().to_string()))
+            .collect()
+    }
+    pub fn to_table(&self) -> String {
+        let mut rows = vec![[
+            "key".to_string(),
+            "type".to_string(),
+            "count".to_string(),
+            "null_ratio".to_string(),
+            "range".to_string(),
+        ]];
+        for (k, v) in self.output_items() {
+            let total = v.total_count();
+            let dominant = v.dominant_type();
+            rows.push([
+                if k.is_empty() { "." } else { k }.to_string(),
+                dominant.to_string(),
+                total.to_string(),
+                format!("{:.2}", v.null_ratio()),
+                v.range(dominant).unwrap_or_default(),
+            ]);
+        }
+        let mut widths = [0; 5];
+        for row in rows.iter() {
+            for (w, cell) in widths.iter_mut().zip(row.iter()) {
+                *w = (*w).max(cell.chars().count());
+            }
+        }
+        let border: String = widths
+            .iter()
+            .map(|w| format!("+{}", "-".repeat(w + 2)))
+            .collect::<String>()
+            + "+\n";
+        let mut ret = border.clone();
+        for (i, row) in rows.iter().enumerate() {
+            for (w, cell) in widths.iter().zip(row.iter()) {
+                ret.push_str(&format!(
+                    "| {}{} ",
+                    cell,
+                    " ".repeat(w - cell.chars().count())
+                ));
+            }
+            ret.push_str("|\n");
+            if i == 0 {
+                ret.push_str(&border);
+            }
+        }
+        ret.push_str(&border);
+        ret
+    }
+    // the longest of `roots` that `key` starts with
+    fn root_of<'a>(roots: &[&'a str], key: &str) -> &'a str {
+        roots
+            .iter()
+            .filter(|r| key.starts_with(**r))
+            .max_by_key(|r| r.len())
+            .copied()
+            .unwrap_or("")
+    }
+    // Output items with the group each belongs to and the path below the group root.
+    fn grouped_items(&self) -> Vec<(&str, &str, &JsonStatItem)> {
+        let roots = self.root_keys();
+        self.output_items()
+            .map(|(k, v)| {
+                let root = Self::root_of(&roots, k);
+                (root, &k[root.len()..], v)
+            })
+            .collect()
+    }
+    /// A copy keeping only the root keys and the keys matching `pattern`, a glob as in
+    /// `JsonStatConfig::key_filter`.
+    pub fn filter(&self, pattern: &str) -> JsonStat {
+        let patterns = [pattern.to_string()];
+        let roots = self.root_keys();
+        let mut ret = self.clone();
+        ret.items.retain(|k, _| {
+            self.config
+                .match_key(&patterns, Self::root_of(&roots, k), k)
+                .0
+        });
+        ret.last_seen.retain(|k, _| ret.items.contains_key(k));
+        ret.type_changes.retain(|k, _| ret.items.contains_key(k));
+        for view in ret.views.values_mut() {
+            *view = view.filter(pattern);
+        }
+        ret
+    }
+    /// One row per key and type seen, with min/max where the type has a range (string
+    /// and array ranges are lengths). Grouped stats get a leading group column.
+    pub fn to_csv(&self) -> String {
+        let grouped = !self.group_keys.is_empty();
+        let mut ret = String::new();
+        if grouped {
+            ret.push_str("group,");
+        }
+        ret.push_str("key,type,count,min,max\n");
+        for (group, k, v) in self.grouped_items() {
+            for (type_name, count) in v.type_counts() {
+                if count == 0 {
+                    continue;
+                }
+                let (min, max) = v.extremes(type_name).unwrap_or_default();
+                if grouped {
+                    ret.push_str(&csv_field(group));
+                    ret.push(',');
+                }
+                ret.push_str(&format!(
+                    "{},{},{},{},{}\n",
+                    csv_field(if k.is_empty() { "." } else { k }),
+                    type_name,
+                    count,
+                    min,
+                    max
+                ));
+            }
+        }
+        ret
+    }
+    /// GitHub-flavoured Markdown table of each key's types, total count and the range of
+    /// its dominant type; grouped stats get one table per group under a `###` heading.
+    pub fn to_markdown(&self) -> String {
+        let mut groups: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for (group, k, v) in self.grouped_items() {
+            let types: Vec<&str> = v
+                .type_counts()
+                .iter()
+                .filter(|(_, count)| *count > 0)
+                .map(|(name, _)| *name)
+                .collect();
+            let (min, max) = v.extremes(v.dominant_type()).unwrap_or_default();
+            groups.entry(group).or_default().push(format!(
+                "| {} | {} | {} | {} | {} |\n",
+                if k.is_empty() { "." } else { k }.replace('|', "\\|"),
+                types.join(", "),
+                v.total_count(),
+                min,
+                max
+            ));
+        }
+        let header = "| key | types | count | min | max |\n|---|---|---|---|---|\n";
+        if self.group_keys.is_empty() {
+            return header.to_string() + &groups.into_values().flatten().collect::<String>();
+        }
+        let mut ret = String::new();
+        for (group, rows) in groups {
+            if !ret.is_empty() {
+                ret.push('\n');
+            }
+            ret.push_str(&format!(
+                "### {}\n\n",
+                if group.is_empty() {
+                    "(no group)"
+                } else {
+                    group
+                }
+            ));
+            ret.push_str(header);
+            ret.extend(rows);
+        }
+        ret
+    }
+    pub fn to_ndjson(&self) -> String {
+        let mut ret = String::new();
+        for (k, v) in self.output_items() {
+            ret.push_str(&json!({"key": k, "stats": v.to_json_value(&self.config)}).to_string());
+            ret.push('\n');
+        }
+        ret
     }
 }
 impl Display for JsonStat {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for (k, v) in self.items.iter() {
-            writeln!(f, "{} : {}", if k.is_empty() { "." } else { k }, v)?;
+        for (k, v) in self.output_items() {
+            write!(f, "{} : ", if k.is_empty() { "." } else { k })?;
+            v.write_summary(f, &self.config)?;
+            writeln!(f)?;
         }
         Ok(())
     }
@@ -331,3 +2763,1353 @@ impl Default for JsonStat {
         Self::new()
     }
 }
+// Carries the group key, the record/line counters and the per-key stats; the config
+// can't be serialized, so a deserialized stat starts out with the default config.
+impl Serialize for JsonStat {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut s = serializer.serialize_struct("JsonStat", 5)?;
+        s.serialize_field("group_keys", &self.group_keys)?;
+        s.serialize_field("record_count", &self.record_count)?;
+        s.serialize_field("line_count", &self.line_count)?;
+        s.serialize_field("failed_line_count", &self.failed_line_count)?;
+        s.serialize_field("items", &self.items)?;
+        s.end()
+    }
+}
+impl<'de> Deserialize<'de> for JsonStat {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Serialized {
+            #[serde(default)]
+            group_keys: Vec<String>,
+            #[serde(default)]
+            record_count: usize,
+            #[serde(default)]
+            line_count: usize,
+            #[serde(default)]
+            failed_line_count: usize,
+            items: BTreeMap<String, JsonStatItem>,
+        }
+        let s = Serialized::deserialize(deserializer)?;
+        Ok(JsonStat {
+            items: s.items,
+            group_keys: s.group_keys,
+            record_count: s.record_count,
+            line_count: s.line_count,
+            failed_line_count: s.failed_line_count,
+            ..JsonStat::new()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(stat: &JsonStat) -> Value {
+        serde_json::from_str(&stat.to_json_str(false)).unwrap()
+    }
+
+    #[test]
+    fn flat_fast_path_matches_general_path() {
+        let records = [
+            json!({"a": 1, "b": "x", "c": 2.5, "d": null, "e": true}),
+            json!({"c": 3, "a": -1, "f": "y"}),
+            json!({}),
+        ];
+        let configs = [
+            JsonStatConfig::default(),
+            JsonStatConfig {
+                key_limit: Some(2),
+                ..Default::default()
+            },
+            JsonStatConfig {
+                key_limit: Some(3),
+                key_overflow: KeyOverflow::Aggregate,
+                track_extreme_examples: true,
+                track_seen_range: true,
+                ..Default::default()
+            },
+        ];
+        for config in configs {
+            let mut fast = JsonStat::new_with_config(config.clone());
+            let mut general = JsonStat::new_with_config(config);
+            let (mut fast_leaves, mut general_leaves) = (Vec::new(), Vec::new());
+            for record in &records {
+                fast.stat_value_with(record, &mut |k, _| fast_leaves.push(k.to_string()));
+                let root = general.config.limit_key(general.get_group_key(record));
+                general.stat_tree(root, record, &mut |k, _| general_leaves.push(k.to_string()));
+                general.record_count += 1;
+            }
+            assert_eq!(fast.to_json_str(true), general.to_json_str(true));
+            assert_eq!(fast.to_json_str(false), general.to_json_str(false));
+            assert_eq!(fast.overflow_count, general.overflow_count);
+            assert_eq!(fast_leaves, general_leaves);
+        }
+    }
+
+    #[test]
+    fn float_precision_rounds_output_only() {
+        let config = JsonStatConfig {
+            float_precision: Some(2),
+            ..Default::default()
+        };
+        let mut a = JsonStat::new_with_config(config.clone());
+        a.stat_str(r#"{"f": 0.1, "i": 1}"#);
+        a.stat_str(r#"{"f": 0.2, "i": 2}"#);
+        let mut b = JsonStat::new_with_config(config);
+        b.stat_str(r#"{"f": 0.30000000000000004, "i": 2}"#);
+        let out = summary(&a);
+        assert_eq!(out[".i"]["int"]["mean"], 1.5);
+        assert_eq!(out[".f"]["float"]["mean"], 0.15);
+        a.merge(&b);
+        let out = summary(&a);
+        assert_eq!(out[".f"]["float"]["max"], 0.3);
+        assert_eq!(out[".i"]["int"]["mean"], 1.67);
+        assert_eq!(
+            a.get(".f").unwrap().float_range(),
+            Some((0.1, 0.30000000000000004))
+        );
+        let display = a.to_string();
+        assert!(display.contains("mean:1.67,"), "{}", display);
+    }
+
+    #[test]
+    fn long_nested_keys_truncate_and_fold() {
+        let config = JsonStatConfig {
+            max_key_length: Some(16),
+            ..Default::default()
+        };
+        let long = "k".repeat(40);
+        let record = json!({ &long: { "a": 1, "b": { "c": "x" } }, "short": 2 });
+        let mut stat = JsonStat::new_with_config(config.clone());
+        stat.stat_value(&record);
+        stat.stat_value(&record);
+        let truncated = format!(".{}{}", "k".repeat(15), TRUNCATED_KEY_MARKER);
+        let keys: Vec<_> = stat.iter().map(|(k, _)| k.to_string()).collect();
+        assert_eq!(keys, ["", &truncated, ".short"]);
+        assert!(keys
+            .iter()
+            .all(|k| k.len() <= 16 + TRUNCATED_KEY_MARKER.len()));
+        // the object, both members and the nested string all fold into one entry
+        let item = stat.get(&truncated).unwrap();
+        assert_eq!(item.object_count(), 4);
+        assert_eq!(item.int_count(), 2);
+        assert_eq!(item.string_count(), 2);
+
+        let mut merged = JsonStat::new_with_config(config.clone());
+        for _ in 0..2 {
+            let mut part = JsonStat::new_with_config(config.clone());
+            part.stat_value(&record);
+            merged.merge(&part);
+        }
+        assert_eq!(merged.to_json_str(true), stat.to_json_str(true));
+    }
+
+    #[test]
+    fn zero_one_ints_are_bool_like() {
+        let mut stat = JsonStat::new();
+        for (flag, level) in [(0, 0), (1, 1), (0, 2), (1, 1)] {
+            stat.stat_value(&json!({ "flag": flag, "level": level }));
+        }
+        let out = summary(&stat);
+        assert_eq!(out[".flag"]["int"]["bool_like"], true);
+        assert!(out[".level"]["int"].get("bool_like").is_none());
+    }
+
+    #[test]
+    fn ndjson_has_one_valid_line_per_key() {
+        let mut stat = JsonStat::new();
+        stat.stat_str(r#"{"a": 1, "b": {"c": [true, null]}}"#);
+        stat.stat_str(r#"{"a": "x", "d": 1.5}"#);
+        let ndjson = stat.to_ndjson();
+        let lines: Vec<Value> = ndjson
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), stat.key_count());
+        let out = summary(&stat);
+        for (line, (key, _)) in lines.iter().zip(stat.iter()) {
+            assert_eq!(line["key"], key);
+            assert_eq!(line["stats"], out[key]);
+        }
+    }
+
+    #[test]
+    fn object_field_counts_histogram() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            object_field_histogram: true,
+            ..Default::default()
+        });
+        for record in [
+            r#"{"o": {}}"#,
+            r#"{"o": {"a": 1}}"#,
+            r#"{"o": {"a": 1, "b": 2}}"#,
+        ] {
+            stat.stat_str(record);
+        }
+        stat.stat_str(r#"{"o": {"c": 3, "d": 4}}"#);
+        let out = summary(&stat);
+        assert_eq!(
+            out[".o"]["object"]["field_count_histogram"],
+            json!({"0": 1, "1": 1, "2": 2})
+        );
+        assert_eq!(out[""]["object"]["field_count_histogram"], json!({"1": 4}));
+    }
+
+    #[test]
+    fn last_seen_index_of_early_field() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            track_last_seen: true,
+            ..Default::default()
+        });
+        stat.stat_str(r#"{"early": 1, "always": 1}"#);
+        stat.stat_str(r#"{"early": 2, "always": 2}"#);
+        for i in 0..5 {
+            stat.stat_value(&json!({ "always": i }));
+        }
+        let last_seen = stat.last_seen_index();
+        assert_eq!(last_seen[".early"], 1);
+        assert!(last_seen[".early"] < stat.record_count());
+        assert_eq!(last_seen[".always"], stat.record_count() - 1);
+    }
+
+    #[test]
+    fn root_shapes_are_counted() {
+        let mut stat = JsonStat::new();
+        for line in [
+            r#"{"a": 1}"#,
+            "[1, 2]",
+            r#"{"b": 2}"#,
+            "[]",
+            "3",
+            r#""s""#,
+            "{}",
+        ] {
+            stat.stat_str(line);
+        }
+        assert_eq!(
+            stat.root_shape_counts(),
+            RootShapeCounts {
+                object: 3,
+                array: 2,
+                scalar: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn leaves_only_drops_container_nodes() {
+        let line = r#"{"a": {"b": 1, "c": [true]}, "d": "x"}"#;
+        let mut all = JsonStat::new();
+        all.stat_str(line);
+        let mut leaves = JsonStat::new_with_config(JsonStatConfig {
+            leaves_only: true,
+            ..Default::default()
+        });
+        leaves.stat_str(line);
+        let (all, leaves) = (summary(&all), summary(&leaves));
+        for key in ["", ".a", ".a.c"] {
+            assert!(all.get(key).is_some());
+            assert!(leaves.get(key).is_none(), "{}", key);
+        }
+        for key in [".a.b", ".a.c[]", ".d"] {
+            assert_eq!(leaves[key], all[key]);
+        }
+    }
+
+    #[test]
+    fn write_json_matches_to_json_str() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            count_absent: true,
+            ..Default::default()
+        });
+        stat.stat_str(r#"{"a": 1, "b": {"c": [1.5, "x"]}}"#);
+        stat.stat_str(r#"{"a": null}"#);
+        for full in [false, true] {
+            let mut buf = Vec::new();
+            stat.write_json(&mut buf, full).unwrap();
+            let written: Value = serde_json::from_slice(&buf).unwrap();
+            let built: Value = serde_json::from_str(&stat.to_json_str(full)).unwrap();
+            assert_eq!(written, built);
+            let pretty: Value = serde_json::from_str(&stat.to_json_str_pretty(full)).unwrap();
+            assert_eq!(pretty, built);
+            let mut old = Vec::new();
+            #[allow(deprecated)]
+            stat.write_json_to(&mut old, full).unwrap();
+            assert_eq!(old, buf);
+        }
+    }
+
+    #[test]
+    fn mixed_numbers_report_a_unified_range() {
+        let mut stat = JsonStat::new();
+        stat.stat_str(r#"{"n": 1}"#);
+        stat.stat_str(r#"{"n": 2.5}"#);
+        stat.stat_str(r#"{"i": 3}"#);
+        let out = summary(&stat);
+        assert_eq!(
+            out[".n"]["number"],
+            json!({"count": 2, "min": 1, "max": 2.5})
+        );
+        assert!(out[".i"].get("number").is_none());
+    }
+
+    #[test]
+    fn escaped_member_names_dont_collide_with_array_paths() {
+        let line = r#"{"a[]": "member", "a": ["element"]}"#;
+        let mut plain = JsonStat::new();
+        plain.stat_str(line);
+        assert_eq!(plain.get(".a[]").unwrap().string_count(), 2);
+
+        let mut escaped = JsonStat::new_with_config(JsonStatConfig {
+            escape_keys: true,
+            ..Default::default()
+        });
+        escaped.stat_str(line);
+        assert_eq!(escaped.get(".a[]").unwrap().string_count(), 1);
+        assert_eq!(escaped.get(r".a\[\]").unwrap().string_count(), 1);
+
+        let mut separated = JsonStat::new_with_config(JsonStatConfig {
+            separator: Some("/".to_string()),
+            array_marker: Some("/*".to_string()),
+            ..Default::default()
+        });
+        separated.stat_str(r#"{"a[b]": 1, "a": [2], "c.d": 3}"#);
+        let keys: Vec<_> = separated.iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, ["", "/a", "/a/*", "/a[b]", "/c.d"]);
+    }
+
+    #[test]
+    fn metrics_select_type_block_fields() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            metrics: Some(vec!["count".to_string(), "mean".to_string()]),
+            quantiles: Some(100),
+            ..Default::default()
+        });
+        for i in 0..10 {
+            stat.stat_value(&json!({ "n": i, "s": "x" }));
+        }
+        let out = summary(&stat);
+        assert_eq!(out[".n"]["int"], json!({"count": 10, "mean": 4.5}));
+        assert_eq!(out[".s"]["string"], json!({"count": 10}));
+        let quantiles = out[".n"]["quantiles"].as_object().unwrap();
+        assert!(!quantiles.is_empty());
+    }
+
+    #[test]
+    fn views_bucket_each_group_key_from_one_pass() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            views: vec!["type".to_string(), "region".to_string()],
+            ..Default::default()
+        });
+        for (kind, region, v) in [("a", "eu", 1), ("b", "eu", 2), ("a", "us", 3)] {
+            stat.stat_value(&json!({ "type": kind, "region": region, "v": v }));
+        }
+        assert_eq!(stat.get(".v").unwrap().int_count(), 3);
+        let by_type = stat.view("type").unwrap();
+        assert_eq!(by_type.get("a.v").unwrap().int_range(), Some((1, 3)));
+        assert_eq!(by_type.get("b.v").unwrap().int_range(), Some((2, 2)));
+        let by_region = stat.view("region").unwrap();
+        assert_eq!(by_region.get("eu.v").unwrap().int_range(), Some((1, 2)));
+        assert_eq!(by_region.get("us.v").unwrap().int_count(), 1);
+        assert_eq!(by_region.record_count(), 3);
+        assert!(stat.view("missing").is_none());
+    }
+
+    #[test]
+    fn array_depth_histogram() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            array_depth_histogram: true,
+            ..Default::default()
+        });
+        for line in [
+            r#"{"a": [1, 2]}"#,
+            r#"{"a": []}"#,
+            r#"{"a": [[1], 2]}"#,
+            r#"{"a": [[[1]], [2]]}"#,
+        ] {
+            stat.stat_str(line);
+        }
+        let out = summary(&stat);
+        assert_eq!(
+            out[".a"]["array"]["depth_histogram"],
+            json!({"1": 2, "2": 1, "3": 1})
+        );
+    }
+
+    #[test]
+    fn shapes_are_counted_per_group() {
+        let mut stat = JsonStat::new_by_group_with_config(
+            "kind",
+            JsonStatConfig {
+                track_shapes: true,
+                ..Default::default()
+            },
+        );
+        for line in [
+            r#"{"kind": "a", "x": 1}"#,
+            r#"{"x": 2, "kind": "a"}"#,
+            r#"{"kind": "a", "x": 3, "y": 4}"#,
+            r#"{"kind": "b", "z": 5}"#,
+        ] {
+            stat.stat_str(line);
+        }
+        let shape = |keys: &[&str]| keys.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+        let shapes = stat.shape_counts();
+        let a = &shapes["a"];
+        assert_eq!(a.len(), 2);
+        assert_eq!(a[&shape(&["kind", "x"])], 2);
+        assert_eq!(a[&shape(&["kind", "x", "y"])], 1);
+        assert_eq!(shapes["b"][&shape(&["kind", "z"])], 1);
+    }
+
+    #[test]
+    fn datetime_extremes_are_the_earliest_and_latest() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            detect_datetime: true,
+            ..Default::default()
+        });
+        for t in [
+            "2024-03-01T10:00:00Z",
+            "2024-01-01T12:00:00+02:00",
+            "2024-01-01T11:00:00Z",
+            "2025-06-30",
+            "not a date",
+        ] {
+            stat.stat_value(&json!({ "t": t }));
+        }
+        let out = summary(&stat);
+        assert_eq!(
+            out[".t"]["string"]["datetime"],
+            json!({"count": 4, "min": "2024-01-01T12:00:00+02:00", "max": "2025-06-30"})
+        );
+    }
+
+    #[test]
+    fn table_has_a_header_and_a_row_per_key() {
+        let mut stat = JsonStat::new();
+        stat.stat_str(r#"{"type": "a", "v": 1, "o": {"n": null}}"#);
+        let table = stat.to_table();
+        let rows: Vec<&str> = table.lines().filter(|l| l.starts_with('|')).collect();
+        assert_eq!(rows.len(), 1 + stat.key_count());
+        let header: Vec<&str> = rows[0].split('|').map(str::trim).collect();
+        assert_eq!(
+            header,
+            ["", "key", "type", "count", "null_ratio", "range", ""]
+        );
+        assert!(rows[1..]
+            .iter()
+            .any(|r| r.starts_with("| .v ") && r.contains("| int ")));
+    }
+
+    #[test]
+    fn merging_serialized_partials_matches_merge() {
+        let partial = |lines: &[&str]| {
+            let mut stat = JsonStat::new();
+            for line in lines {
+                stat.stat_str(line);
+            }
+            stat
+        };
+        let a = partial(&[r#"{"a": 1, "b": "x"}"#, "bad"]);
+        let b = partial(&[r#"{"a": 2.5, "c": [true]}"#]);
+        let mut direct = JsonStat::new();
+        direct.merge(&a);
+        direct.merge(&b);
+
+        let mut serialized = JsonStat::new();
+        for part in [&a, &b] {
+            serialized
+                .merge_serialized(&serde_json::to_vec(part).unwrap())
+                .unwrap();
+        }
+        assert_eq!(serialized.to_json_str(true), direct.to_json_str(true));
+        assert_eq!(serialized.to_json_str(false), direct.to_json_str(false));
+        assert_eq!(serialized.record_count(), 2);
+        assert_eq!(serialized.line_count(), 3);
+        assert_eq!(serialized.failed_line_count(), 1);
+
+        let mut items_only = JsonStat::new();
+        for part in [&a, &b] {
+            items_only
+                .merge_serialized(part.to_json_str(true).as_bytes())
+                .unwrap();
+        }
+        assert_eq!(items_only.to_json_str(true), direct.to_json_str(true));
+
+        #[cfg(feature = "msgpack")]
+        {
+            let mut packed = JsonStat::new();
+            for part in [&a, &b] {
+                packed.merge_serialized(&part.to_msgpack()).unwrap();
+            }
+            assert_eq!(packed.to_json_str(false), direct.to_json_str(false));
+        }
+    }
+
+    #[test]
+    fn merging_serialized_partials_respects_key_limit() {
+        let mut part = JsonStat::new();
+        part.stat_str(r#"{"a": 1, "b": 2, "c": 3}"#);
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            key_limit: Some(2),
+            ..Default::default()
+        });
+        stat.merge_serialized(&serde_json::to_vec(&part).unwrap())
+            .unwrap();
+        // the root and `.a` take both slots
+        assert_eq!(stat.key_count(), 2);
+        assert_eq!(stat.overflow_count(), 2);
+    }
+
+    #[test]
+    fn always_empty_containers_are_flagged() {
+        let mut stat = JsonStat::new();
+        stat.stat_str(r#"{"never": [], "sometimes": [1], "obj": {}}"#);
+        stat.stat_str(r#"{"never": [], "sometimes": [], "obj": {}}"#);
+        let out = summary(&stat);
+        assert_eq!(out[".never"]["array"]["always_empty"], true);
+        assert_eq!(out[".sometimes"]["array"]["always_empty"], false);
+        assert_eq!(out[".sometimes"]["array"]["empty"], 1);
+        assert_eq!(out[".obj"]["object"]["always_empty"], true);
+    }
+
+    #[test]
+    fn throughput_is_reported_when_tracked() {
+        let line = r#"{"a": 1, "b": "text", "c": [1, 2, 3]}"#;
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            track_throughput: true,
+            ..Default::default()
+        });
+        for _ in 0..1000 {
+            stat.stat_str(line);
+        }
+        assert_eq!(stat.byte_count(), 1000 * line.len());
+        let (records, bytes) = stat.throughput();
+        assert!(records > 0.0 && bytes > 0.0);
+        assert!((bytes / records - line.len() as f64).abs() < 1e-6);
+
+        let mut untracked = JsonStat::new();
+        untracked.stat_str(line);
+        assert_eq!(untracked.throughput(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn deepest_and_widest_records() {
+        let mut stat = JsonStat::new();
+        stat.stat_str(r#"{"a": {"b": {"c": [[1]]}}, "x": 1}"#);
+        let wide: Map<String, Value> = (0..50).map(|i| (format!("k{}", i), json!(i))).collect();
+        stat.stat_value(&Value::Object(wide));
+        stat.stat_str(r#"{"y": [1, 2, 3]}"#);
+        assert_eq!(stat.max_record_depth(), 5);
+        assert_eq!(stat.max_record_width(), 50);
+        let branches = stat.max_branch_depths();
+        assert_eq!(branches[".a"], 4);
+        assert_eq!(branches[".y"], 1);
+        assert!(!branches.contains_key(".x"));
+    }
+
+    #[test]
+    fn record_types_keeps_only_matching_leaves() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            record_types: Some(vec![ValueType::Number]),
+            ..Default::default()
+        });
+        stat.stat_str(r#"{"name": "x", "n": 1, "o": {"s": "y", "f": 2.5, "a": [3, "z"]}}"#);
+        let keys: Vec<_> = stat.iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, [".n", ".o.a[]", ".o.f"]);
+        let element = stat.get(".o.a[]").unwrap();
+        assert_eq!((element.int_count(), element.string_count()), (1, 0));
+    }
+
+    #[test]
+    fn reset_extremes_keeps_counts() {
+        let mut stat = JsonStat::new();
+        for x in [10, 20, 30] {
+            stat.stat_value(&json!({ "x": x }));
+        }
+        stat.reset_extremes();
+        let out = summary(&stat);
+        assert_eq!(out[".x"]["int"]["count"], 3);
+        assert!(out[".x"]["int"].get("min").is_none());
+        assert_eq!(stat.get(".x").unwrap().int_range(), None);
+
+        stat.stat_value(&json!({"x": 15}));
+        stat.stat_value(&json!({"x": 12}));
+        let item = stat.get(".x").unwrap();
+        assert_eq!(item.int_count(), 5);
+        assert_eq!(item.int_range(), Some((12, 15)));
+    }
+
+    #[test]
+    fn reset_extremes_survive_serialization() {
+        let mut reset = JsonStat::new();
+        reset.stat_value(&json!({"x": 1}));
+        reset.reset_extremes();
+        let reloaded = JsonStat::from_json_str(&serde_json::to_string(&reset).unwrap()).unwrap();
+        let mut live = JsonStat::new();
+        live.stat_value(&json!({"x": 50}));
+        live.merge(&reloaded);
+        let item = live.get(".x").unwrap();
+        assert_eq!(item.int_count(), 2);
+        assert_eq!(item.int_range(), Some((50, 50)));
+
+        let mut into_reset = reloaded;
+        into_reset.merge(&live);
+        assert_eq!(into_reset.get(".x").unwrap().int_range(), Some((50, 50)));
+    }
+
+    #[test]
+    fn type_change_point_is_the_first_differing_record() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            track_type_changes: true,
+            ..Default::default()
+        });
+        for i in 0..3 {
+            stat.stat_value(&json!({ "id": i, "stable": true }));
+        }
+        stat.stat_value(&json!({"id": "x3", "stable": false}));
+        stat.stat_value(&json!({"id": 4}));
+        let changes = stat.type_change_points();
+        assert_eq!(changes.get(".id"), Some(&3));
+        assert!(!changes.contains_key(".stable"));
+    }
+
+    #[test]
+    fn consistent_tuples_get_per_position_types() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            array_index_limit: Some(4),
+            ..Default::default()
+        });
+        for line in [
+            r#"{"point": ["a", 1, true], "list": [1, 2]}"#,
+            r#"{"point": ["b", 2, false], "list": [1, 2, 3]}"#,
+        ] {
+            stat.stat_str(line);
+        }
+        let tuples = stat.tuple_types();
+        assert_eq!(tuples.get(".point"), Some(&vec!["string", "int", "bool"]));
+        assert!(!tuples.contains_key(".list"));
+    }
+
+    #[test]
+    fn redaction_hides_captured_values_but_keeps_lengths() {
+        let config = JsonStatConfig {
+            top_n: Some(5),
+            samples: true,
+            detect_datetime: true,
+            track_extreme_examples: true,
+            ..Default::default()
+        };
+        let lines = [
+            r#"{"s": "secret-alpha", "n": 41, "t": "2024-01-01T00:00:00Z"}"#,
+            r#"{"s": "secret-beta!", "n": 42, "t": "2024-02-01T00:00:00Z"}"#,
+        ];
+        let mut plain = JsonStat::new_with_config(config.clone());
+        let mut redacted = JsonStat::new_with_config(JsonStatConfig {
+            redact_values: true,
+            ..config
+        });
+        for line in lines {
+            plain.stat_str(line);
+            redacted.stat_str(line);
+        }
+        let plain_out = plain.to_json_str(true) + &plain.to_json_str(false);
+        let redacted_out = redacted.to_json_str(true) + &redacted.to_json_str(false);
+        for raw in ["secret", "2024-0"] {
+            assert!(plain_out.contains(raw), "{}", raw);
+            assert!(!redacted_out.contains(raw), "{}", raw);
+        }
+        // int min/max are stats, but the values kept alongside them are captured
+        let int = &redacted.get(".n").unwrap().int;
+        assert!(int.min_example.as_ref().unwrap().is_string());
+        let (plain, redacted) = (summary(&plain), summary(&redacted));
+        for captured in ["top", "samples"] {
+            assert!(plain[".n"][captured].to_string().contains("41"));
+            let values = redacted[".n"][captured].to_string();
+            assert!(
+                !values.contains("41") && !values.contains("42"),
+                "{}",
+                values
+            );
+        }
+        assert_eq!(redacted[".s"]["string"]["count"], 2);
+        for field in ["min", "max", "empty"] {
+            assert_eq!(
+                redacted[".s"]["string"][field],
+                plain[".s"]["string"][field]
+            );
+        }
+        assert_eq!(redacted[".n"]["int"], plain[".n"]["int"]);
+    }
+
+    #[test]
+    fn from_json_str_round_trips_full_output() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            distinct: true,
+            quantiles: Some(50),
+            top_n: Some(3),
+            ..Default::default()
+        });
+        for i in 0..100 {
+            stat.stat_value(&json!({ "n": i, "f": i as f64 / 3.0, "s": format!("v{}", i % 7) }));
+        }
+        let read = JsonStat::from_json_str(&stat.to_json_str(true)).unwrap();
+        assert_eq!(read.to_json_str(true), stat.to_json_str(true));
+        let mut merged = JsonStat::new();
+        merged.merge(&read);
+        merged.merge(&stat);
+        let mut twice = stat.clone();
+        twice.merge(&stat);
+        assert_eq!(merged.to_json_str(true), twice.to_json_str(true));
+        assert!(JsonStat::from_json_str("[1]").is_err());
+    }
+
+    #[test]
+    fn integers_past_i64() {
+        let mut stat = JsonStat::new();
+        stat.stat_str(r#"{"n": 18446744073709551615}"#);
+        stat.stat_str(r#"{"n": 18446744073709551615}"#);
+        stat.stat_str(r#"{"n": -9223372036854775808}"#);
+        let item = stat.get(".n").unwrap();
+        assert_eq!(item.int_count(), 3);
+        assert_eq!(item.int_range(), Some((i64::MIN as i128, u64::MAX as i128)));
+        let int = &summary(&stat)[".n"]["int"];
+        assert_eq!(int["max"], json!(u64::MAX));
+        assert_eq!(int["min"], json!(i64::MIN));
+        // the sum doesn't fit a JSON integer, so it is written as a float
+        assert_eq!(int["sum"], json!(2.0 * u64::MAX as f64 + i64::MIN as f64));
+        assert!(stat
+            .to_string()
+            .contains("int:3(-9223372036854775808~18446744073709551615,"));
+        let read = JsonStat::from_json_str(&stat.to_json_str(true)).unwrap();
+        assert_eq!(read.to_json_str(true), stat.to_json_str(true));
+    }
+
+    #[test]
+    fn group_keys_of_every_scalar_type() {
+        let mut stat = JsonStat::new_by_group("g");
+        for line in [
+            r#"{"g": "s", "v": 1}"#,
+            r#"{"g": 7, "v": 1}"#,
+            r#"{"g": 2.5, "v": 1}"#,
+            r#"{"g": true, "v": 1}"#,
+            r#"{"g": null, "v": 1}"#,
+            r#"{"g": [1], "v": 1}"#,
+            r#"{"v": 1}"#,
+        ] {
+            stat.stat_str(line);
+        }
+        for group in ["s", "7", "2.5", "true", NULL_GROUP_VALUE] {
+            let key = format!("{}.v", group);
+            assert_eq!(stat.get(&key).map(|v| v.int_count()), Some(1), "{}", key);
+        }
+        // containers and missing paths both land in the ungrouped root
+        assert_eq!(stat.get(".v").unwrap().int_count(), 2);
+    }
+
+    #[test]
+    fn multiple_group_keys_join_their_values() {
+        let mut stat = JsonStat::new_by_groups(&["a", "b.c"]);
+        stat.stat_str(r#"{"a": 1, "b": {"c": false}, "v": 1}"#);
+        stat.stat_str(r#"{"a": "x", "v": 2}"#);
+        let sep = JsonStatConfig::default().group_separator().to_string();
+        assert!(stat.get(&format!("1{}false.v", sep)).is_some());
+        assert!(stat
+            .get(&format!("x{}{}.v", sep, MISSING_GROUP_VALUE))
+            .is_some());
+    }
+
+    #[test]
+    fn clear_matches_a_fresh_instance() {
+        let config = JsonStatConfig {
+            track_last_seen: true,
+            distinct: true,
+            ..Default::default()
+        };
+        let mut stat = JsonStat::new_by_group_with_config("g", config.clone());
+        stat.stat_str(r#"{"g": "a", "old": [1, 2]}"#);
+        stat.stat_str("bad");
+        stat.clear();
+        assert!(stat.is_empty());
+        assert_eq!((stat.line_count(), stat.failed_line_count()), (0, 0));
+        let mut fresh = JsonStat::new_by_group_with_config("g", config);
+        for s in [&mut stat, &mut fresh] {
+            s.stat_str(r#"{"g": "b", "new": 1}"#);
+        }
+        assert_eq!(stat.to_json_str(false), fresh.to_json_str(false));
+        assert_eq!(stat.to_json_str(true), fresh.to_json_str(true));
+        assert_eq!(stat.last_seen_index(), fresh.last_seen_index());
+    }
+
+    #[test]
+    fn integral_floats_stay_floats_by_default() {
+        let lines = [r#"{"n": 1.0}"#, r#"{"n": 1}"#, r#"{"n": 1.5}"#];
+        let mut split = JsonStat::new();
+        let mut folded = JsonStat::new_with_config(JsonStatConfig {
+            integral_floats_as_int: true,
+            ..Default::default()
+        });
+        for line in lines {
+            split.stat_str(line);
+            folded.stat_str(line);
+        }
+        let split = split.get(".n").unwrap();
+        assert_eq!((split.int_count(), split.float_count()), (1, 2));
+        assert_eq!(split.float_range(), Some((1.0, 1.5)));
+        let folded = folded.get(".n").unwrap();
+        assert_eq!((folded.int_count(), folded.float_count()), (2, 1));
+        assert_eq!(folded.int_range(), Some((1, 1)));
+        assert_eq!(folded.float_range(), Some((1.5, 1.5)));
+    }
+
+    #[test]
+    fn nan_and_infinity_values_are_safe() {
+        // serde_json can't hold nonfinite numbers, so a NaN `Value` is already null
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            quantiles: Some(50),
+            histogram: Some(HistogramConfig {
+                buckets: 4,
+                range: None,
+            }),
+            ..Default::default()
+        });
+        stat.stat_value(&json!({"n": f64::NAN, "i": f64::INFINITY}));
+        stat.stat_value(&json!({"n": 1.5, "i": f64::NEG_INFINITY}));
+        assert!(!stat.stat_str(r#"{"n": NaN}"#));
+        let n = stat.get(".n").unwrap();
+        assert_eq!((n.null_count(), n.float_count()), (1, 1));
+        assert_eq!(n.float_range(), Some((1.5, 1.5)));
+        assert_eq!(stat.get(".i").unwrap().null_count(), 2);
+        let out = summary(&stat);
+        assert_eq!(out[".n"]["float"]["mean"], 1.5);
+        assert!(out[".n"]["float"].get("nonfinite").is_none());
+    }
+
+    #[test]
+    fn multibyte_strings_have_distinct_byte_and_char_lengths() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            string_char_lengths: true,
+            ..Default::default()
+        });
+        stat.stat_value(&json!({"s": "héllo"}));
+        stat.stat_value(&json!({"s": "日本語"}));
+        stat.stat_value(&json!({"s": "ab"}));
+        let item = stat.get(".s").unwrap();
+        assert_eq!(item.string_len_range(), Some((2, 9)));
+        assert_eq!(item.string_char_range(), Some((2, 5)));
+        let string = &summary(&stat)[".s"]["string"];
+        assert_eq!((&string["min"], &string["max"]), (&json!(2), &json!(9)));
+        assert_eq!(
+            (&string["min_chars"], &string["max_chars"]),
+            (&json!(2), &json!(5))
+        );
+    }
+
+    #[test]
+    fn number_bucket_round_trips() {
+        let lines = [r#"{"n": 1}"#, r#"{"n": 2.5}"#, r#"{"n": 4}"#];
+        let mut split = JsonStat::new();
+        let config = JsonStatConfig {
+            number_bucket: true,
+            ..Default::default()
+        };
+        let mut bucketed = JsonStat::new_with_config(config.clone());
+        for line in lines {
+            split.stat_str(line);
+            bucketed.stat_str(line);
+        }
+        let out = summary(&split);
+        assert_eq!(out[".n"]["int"]["count"], 2);
+        assert_eq!(out[".n"]["float"]["count"], 1);
+        let out = summary(&bucketed);
+        assert!(out[".n"].get("int").is_none() && out[".n"].get("float").is_none());
+        let number = &out[".n"]["number"];
+        assert_eq!(
+            (&number["count"], &number["min"], &number["max"]),
+            (&json!(3), &json!(1.0), &json!(4.0))
+        );
+        assert_eq!(number["sum"], 7.5);
+        assert!(bucketed.to_string().contains(".n : number:3(1~4,mean:2.5,"));
+
+        let read = JsonStat::from_json_str(&serde_json::to_string(&bucketed).unwrap()).unwrap();
+        let read = JsonStat { config, ..read };
+        assert_eq!(read.to_json_str(false), bucketed.to_json_str(false));
+        // the split is only in the output, so the stored stats are the same either way
+        assert_eq!(bucketed.to_json_str(true), split.to_json_str(true));
+    }
+
+    #[test]
+    fn prefixed_sources_stay_apart_when_merged() {
+        let source = |prefix: &str, line: &str| {
+            let mut stat = JsonStat::new_with_config(JsonStatConfig {
+                root_prefix: Some(prefix.to_string()),
+                ..Default::default()
+            });
+            stat.stat_str(line);
+            stat
+        };
+        let mut merged = source("users", r#"{"name": "ann", "id": 1}"#);
+        merged.merge(&source("orders", r#"{"name": 7}"#));
+        assert_eq!(merged.get("users.name").unwrap().string_count(), 1);
+        assert_eq!(merged.get("orders.name").unwrap().int_count(), 1);
+        assert!(merged.get(".name").is_none());
+        assert_eq!(merged.get("users").unwrap().object_count(), 1);
+
+        let mut grouped = JsonStat::new_by_group_with_config(
+            "kind",
+            JsonStatConfig {
+                root_prefix: Some("events".to_string()),
+                ..Default::default()
+            },
+        );
+        grouped.stat_str(r#"{"kind": "click", "x": 1}"#);
+        grouped.stat_str(r#"{"x": 2}"#);
+        assert!(grouped.get("events.click.x").is_some());
+        assert!(grouped
+            .get(&format!("events.{}.x", MISSING_GROUP_VALUE))
+            .is_some());
+    }
+
+    #[test]
+    fn scalar_roots_are_single_leaves() {
+        let mut stat = JsonStat::new();
+        for line in ["3", "true", r#""s""#, "null", "[1, 2]"] {
+            assert!(stat.stat_str(line), "{}", line);
+        }
+        let root = stat.get("").unwrap();
+        assert_eq!(
+            (
+                root.int_count(),
+                root.bool_count(),
+                root.string_count(),
+                root.null_count()
+            ),
+            (1, 1, 1, 1)
+        );
+        assert_eq!(root.array_count(), 1);
+        let keys: Vec<_> = stat.iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, ["", "[]"]);
+        assert_eq!(stat.record_count(), 5);
+    }
+
+    #[test]
+    fn objects_only_rejects_scalar_roots() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            objects_only: true,
+            ..Default::default()
+        });
+        assert!(!stat.stat_str("3"));
+        assert!(!stat.stat_str("[1]"));
+        assert!(stat.stat_str(r#"{"a": 1}"#));
+        assert!(!stat.stat_value(&json!(false)));
+        assert_eq!(stat.failed_line_count(), 2);
+        assert_eq!(stat.record_count(), 1);
+        assert_eq!(stat.key_count(), 2);
+        assert!(stat.stat_str_result("4").is_err());
+    }
+
+    #[test]
+    fn denied_keys_leave_no_entry() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            deny_keys: Some(vec!["*password".to_string(), "secrets".to_string()]),
+            samples: true,
+            top_n: Some(3),
+            ..Default::default()
+        });
+        stat.stat_str(
+            r#"{"password": "p1", "user": {"password": "p2", "name": "ann"},
+                "secrets": {"token": "t1", "keys": ["k1"]}}"#,
+        );
+        let keys: Vec<_> = stat.iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, ["", ".user", ".user.name"]);
+        let out = stat.to_json_str(true) + &stat.to_json_str(false) + &stat.to_string();
+        for raw in ["p1", "p2", "t1", "k1", "password", "token"] {
+            assert!(!out.contains(raw), "{}", raw);
+        }
+    }
+
+    #[test]
+    fn masked_keys_keep_only_type_counts() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            mask_keys: Some(vec!["ssn".to_string()]),
+            top_n: Some(3),
+            ..Default::default()
+        });
+        stat.stat_str(r#"{"ssn": "123-45-6789", "n": 1}"#);
+        stat.stat_str(r#"{"ssn": 123456789}"#);
+        let out = summary(&stat);
+        assert_eq!(
+            out[".ssn"],
+            json!({"string": {"count": 1}, "int": {"count": 1}, "masked": true})
+        );
+        let full = stat.to_json_str(true);
+        assert!(!full.contains("123-45-6789") && !full.contains("123456789"));
+    }
+
+    #[test]
+    fn case_insensitive_keys_merge_into_one() {
+        let config = JsonStatConfig {
+            lowercase_keys: true,
+            ..Default::default()
+        };
+        let mut a = JsonStat::new_with_config(config.clone());
+        a.stat_str(r#"{"userId": 1}"#);
+        let mut b = JsonStat::new_with_config(config);
+        b.stat_str(r#"{"userid": 2}"#);
+        a.merge(&b);
+        let keys: Vec<_> = a.iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, ["", ".userid"]);
+        assert_eq!(a.get(".userid").unwrap().int_range(), Some((1, 2)));
+
+        let mut normalized = JsonStat::new_with_config(JsonStatConfig {
+            key_normalizer: Some(|name| name.replace('_', "")),
+            lowercase_keys: true,
+            ..Default::default()
+        });
+        normalized.stat_str(r#"{"user_ID": 1, "UserId": 2}"#);
+        assert_eq!(normalized.get(".userid").unwrap().int_count(), 2);
+    }
+
+    #[test]
+    fn split_serialized_sketches_merge_like_a_single_pass() {
+        let config = JsonStatConfig {
+            distinct: true,
+            quantiles: Some(100),
+            histogram: Some(HistogramConfig {
+                buckets: 10,
+                range: Some((0.0, 10_000.0)),
+            }),
+            ..Default::default()
+        };
+        let record = |i: usize| json!({ "n": (i * 7919) % 10_000, "s": format!("v{}", i % 500) });
+        let mut single = JsonStat::new_with_config(config.clone());
+        let mut parts = [
+            JsonStat::new_with_config(config.clone()),
+            JsonStat::new_with_config(config.clone()),
+        ];
+        for i in 0..10_000 {
+            single.stat_value(&record(i));
+            parts[i % 2].stat_value(&record(i));
+        }
+        type Codec = fn(&JsonStat) -> JsonStat;
+        #[cfg_attr(not(feature = "msgpack"), allow(unused_mut))]
+        let mut codecs: Vec<(&str, Codec)> = vec![
+            ("json", |s| {
+                JsonStat::from_json_str(&s.to_json_str(true)).unwrap()
+            }),
+            ("compact", |s| {
+                let mut buf = Vec::new();
+                s.write_compact(&mut buf).unwrap();
+                JsonStat::read_compact(buf.as_slice()).unwrap()
+            }),
+        ];
+        #[cfg(feature = "msgpack")]
+        codecs.push(("msgpack", |s| {
+            let mut ret = JsonStat::new();
+            ret.merge_serialized(&s.to_msgpack()).unwrap();
+            ret
+        }));
+        let expected = summary(&single);
+        for (name, codec) in codecs {
+            let mut merged = JsonStat::new_with_config(config.clone());
+            for part in &parts {
+                merged.merge(&codec(part));
+            }
+            let out = summary(&merged);
+            // HLL registers and histogram counts merge exactly
+            assert_eq!(
+                out[".s"]["distinct"], expected[".s"]["distinct"],
+                "{}",
+                name
+            );
+            assert_eq!(
+                out[".n"]["histogram"], expected[".n"]["histogram"],
+                "{}",
+                name
+            );
+            for q in ["p50", "p95", "p99"] {
+                let (a, b) = (
+                    out[".n"]["quantiles"][q].as_f64(),
+                    expected[".n"]["quantiles"][q].as_f64(),
+                );
+                assert!((a.unwrap() - b.unwrap()).abs() < 100.0, "{} {}", name, q);
+            }
+            let distinct = out[".s"]["distinct"].as_f64().unwrap();
+            assert!((distinct - 500.0).abs() < 25.0, "{}", distinct);
+        }
+    }
+
+    #[test]
+    fn verbose_output_has_the_same_shape_for_any_data() {
+        let lines = [
+            r#"{"k": 1}"#,
+            r#"{"k": 1.5}"#,
+            r#"{"k": "s"}"#,
+            r#"{"k": [null]}"#,
+            r#"{"k": {"b": true}}"#,
+        ];
+        for number_bucket in [false, true] {
+            let config = JsonStatConfig {
+                verbose: true,
+                number_bucket,
+                ..Default::default()
+            };
+            let mut shapes = Vec::new();
+            for (i, line) in lines.iter().enumerate() {
+                let mut stat = JsonStat::new_with_config(config.clone());
+                stat.stat_str(line);
+                stat.stat_str(lines[(i + 1) % lines.len()]);
+                let out = summary(&stat);
+                for item in out.as_object().unwrap().values().filter(|v| v.is_object()) {
+                    let mut keys: Vec<_> = item.as_object().unwrap().keys().cloned().collect();
+                    keys.sort();
+                    let expected: &[&str] = if number_bucket {
+                        &["array", "bool", "null", "number", "object", "string"]
+                    } else {
+                        &["array", "bool", "float", "int", "null", "object", "string"]
+                    };
+                    assert_eq!(keys, expected);
+                    shapes.push(keys);
+                }
+                let display = stat.to_string();
+                for line in display.lines() {
+                    let types: Vec<_> = line
+                        .split(" : ")
+                        .nth(1)
+                        .unwrap()
+                        .split(';')
+                        .map(|t| t.split(':').next().unwrap())
+                        .collect();
+                    let expected: &[&str] = if number_bucket {
+                        &["null", "bool", "number", "string", "array", "object"]
+                    } else {
+                        &["null", "bool", "int", "float", "string", "array", "object"]
+                    };
+                    assert_eq!(types, expected, "{}", line);
+                }
+            }
+            assert!(shapes.windows(2).all(|w| w[0] == w[1]), "{:?}", shapes);
+        }
+    }
+
+    #[test]
+    fn stat_reader_stops_without_error_past_max_keys() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            max_keys: Some(2),
+            ..Default::default()
+        });
+        let input = "{\"a\": 1}\n{\"b\": 1, \"c\": 1}\n{\"d\": 1}\n";
+        assert_eq!(stat.stat_reader(input.as_bytes()).unwrap(), 2);
+        assert!(stat.max_keys_exceeded());
+        assert_eq!(stat.line_count(), 2);
+        assert!(stat.get(".d").is_none());
+        assert!(!stat.stat_str(r#"{"e": 1}"#));
+    }
+
+    #[test]
+    fn weighted_records_scale_counts() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            weight_field: Some("w".to_string()),
+            ..Default::default()
+        });
+        stat.stat_str(r#"{"w": 3, "a": 2, "s": "x"}"#);
+        stat.stat_str(r#"{"a": 5}"#);
+        stat.stat_str(r#"{"w": 0, "a": 100}"#);
+        stat.stat_str(r#"{"w": "two", "a": 1}"#);
+        assert_eq!(stat.record_count(), 5);
+        assert!(stat.get(".w").is_none());
+        let a = stat.get(".a").unwrap();
+        assert_eq!((a.int_count(), a.int_range()), (5, Some((1, 5))));
+        let int = &summary(&stat)[".a"]["int"];
+        assert_eq!(int["sum"], 12);
+        assert_eq!(stat.get(".s").unwrap().string_count(), 3);
+        assert_eq!(stat.root_shape_counts().object, 5);
+    }
+
+    #[test]
+    fn huge_weights_are_capped() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            weight_field: Some("w".to_string()),
+            track_shapes: true,
+            ..Default::default()
+        });
+        let line = format!(r#"{{"w": {}, "a": 1}}"#, u64::MAX);
+        stat.stat_str(&line);
+        stat.stat_str(&line);
+        stat.stat_str(r#"{"a": 2}"#);
+        let cap = MAX_RECORD_WEIGHT as usize;
+        assert_eq!(stat.record_count(), 2 * cap + 1);
+        assert_eq!(stat.get(".a").unwrap().int_count(), 2 * cap + 1);
+        let mut merged = stat.clone();
+        merged.merge(&stat);
+        assert_eq!(merged.record_count(), 4 * cap + 2);
+    }
+
+    #[test]
+    fn sampling_counts_weighted_records_once() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            weight_field: Some("w".to_string()),
+            sample_every: Some(2),
+            ..Default::default()
+        });
+        for i in 0..10 {
+            stat.stat_value(&json!({ "w": 2, "i": i }));
+        }
+        assert_eq!(stat.skipped_record_count(), 5);
+        assert_eq!(stat.record_count(), 10);
+        let i = stat.get(".i").unwrap();
+        assert_eq!((i.int_count(), i.int_range()), (10, Some((0, 8))));
+    }
+
+    #[test]
+    fn sample_every_keeps_one_record_in_n() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            sample_every: Some(3),
+            track_last_seen: true,
+            ..Default::default()
+        });
+        let mut kept = Vec::new();
+        for i in 0..10 {
+            stat.stat_value_with(&json!({ "i": i }), &mut |_, v| kept.push(v.clone()));
+        }
+        assert_eq!(kept, [json!(0), json!(3), json!(6), json!(9)]);
+        assert_eq!((stat.record_count(), stat.skipped_record_count()), (4, 6));
+        assert_eq!(summary(&stat)[SAMPLE_EVERY_KEY], 3);
+
+        // weighted records are still picked one in N, each keeping its weight
+        let mut weighted = JsonStat::new_with_config(JsonStatConfig {
+            sample_every: Some(3),
+            weight_field: Some("w".to_string()),
+            ..Default::default()
+        });
+        for i in 0..6 {
+            weighted.stat_value(&json!({ "w": i + 1, "i": i }));
+        }
+        assert_eq!(weighted.skipped_record_count(), 4);
+        assert_eq!(weighted.record_count(), 1 + 4);
+        let i = weighted.get(".i").unwrap();
+        assert_eq!((i.int_count(), i.int_range()), (5, Some((0, 3))));
+    }
+
+    #[test]
+    fn absent_counts_members_missing_from_their_objects() {
+        let mut stat = JsonStat::new();
+        stat.stat_str(r#"{"a": 1, "o": {"x": 1, "y": {"z": 1}}}"#);
+        stat.stat_str(r#"{"a": null, "o": {"y": {}}}"#);
+        stat.stat_str(r#"{"o": {"x": [1, 2]}}"#);
+        stat.stat_str(r#"{"l": [{"k": 1}, {}, {"k": 2}]}"#);
+        let absent = stat.absent_counts();
+        assert_eq!(absent[".a"], 2);
+        assert_eq!(absent[".o"], 1);
+        assert_eq!(absent[".o.x"], 1);
+        assert_eq!(absent[".o.y"], 1);
+        assert_eq!(absent[".o.y.z"], 1);
+        assert_eq!(absent[".l[].k"], 1);
+        assert!(!absent.contains_key(".o.x[]"));
+    }
+
+    #[test]
+    fn absent_counts_survive_key_folding() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            lowercase_keys: true,
+            weight_field: Some("w".to_string()),
+            ..Default::default()
+        });
+        stat.stat_str(r#"{"o": {"Name": 1, "name": 2}}"#);
+        stat.stat_str(r#"{"o": {"NAME": 3}, "w": 2}"#);
+        stat.stat_str(r#"{"o": {}, "w": 3}"#);
+        assert_eq!(stat.get(".o.name").unwrap().int_count(), 4);
+        assert_eq!(stat.absent_counts()[".o.name"], 3);
+
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            max_depth: Some(2),
+            key_filter: Some(vec!["o".to_string(), "o.*".to_string()]),
+            ..Default::default()
+        });
+        stat.stat_str(r#"{"o": {"p": {"q": 1}}, "skip": 1}"#);
+        stat.stat_str(r#"{"o": {"p": {"q": 2}, "r": 1}}"#);
+        stat.stat_str(r#"{"o": {"r": {"s": 1}}}"#);
+        let absent = stat.absent_counts();
+        assert_eq!(absent[".o"], 0);
+        assert_eq!(absent[".o.p"], 1);
+        assert_eq!(absent[".o.r"], 1);
+        assert!(!stat.iter().any(|(k, _)| k == ".o.p.q" || k == ".skip"));
+
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            max_key_length: Some(6),
+            ..Default::default()
+        });
+        stat.stat_str(r#"{"o": {"long_a": 1, "long_b": 2}}"#);
+        stat.stat_str(r#"{"o": {}}"#);
+        let absent = stat.absent_counts();
+        assert_eq!(absent[".o"], 0);
+        assert!(!absent.keys().any(|k| k.ends_with(TRUNCATED_KEY_MARKER)));
+    }
+
+    #[test]
+    fn absent_counts_treat_unrecorded_types_as_absent() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            record_types: Some(vec![ValueType::Object, ValueType::Number]),
+            ..Default::default()
+        });
+        stat.stat_str(r#"{"o": {"n": 1}}"#);
+        stat.stat_str(r#"{"o": {"n": "x"}}"#);
+        stat.stat_str(r#"{"o": {}}"#);
+        assert_eq!(stat.absent_counts()[".o.n"], 2);
+    }
+
+    #[test]
+    fn stat_array_reports_rejected_elements() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            objects_only: true,
+            ..Default::default()
+        });
+        assert!(stat.stat_array(&json!([{"a": 1}, {"a": 2}])));
+        assert!(!stat.stat_array(&json!([{"a": 3}, 4, {"a": 5}])));
+        assert!(!stat.stat_array(&json!({"a": 6})));
+        assert_eq!(stat.record_count(), 4);
+        assert_eq!(stat.get(".a").unwrap().int_range(), Some((1, 5)));
+    }
+
+    #[test]
+    fn ndjson_has_a_line_for_aggregated_overflow() {
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            key_limit: Some(2),
+            key_overflow: KeyOverflow::Aggregate,
+            ..Default::default()
+        });
+        stat.stat_str(r#"{"a": 1, "b": {"c": [true, null]}}"#);
+        stat.stat_str(r#"{"a": "x", "d": 1.5}"#);
+        let lines: Vec<Value> = stat
+            .to_ndjson()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(stat.key_count(), 2);
+        assert_eq!(lines.len(), 3);
+        let overflow = lines.iter().find(|l| l["key"] == OVERFLOW_KEY).unwrap();
+        let item = stat.get(OVERFLOW_KEY).unwrap();
+        assert_eq!(
+            item.type_counts()
+                .into_iter()
+                .filter(|(_, n)| *n > 0)
+                .collect::<Vec<_>>(),
+            [
+                ("null", 1),
+                ("bool", 1),
+                ("float", 1),
+                ("array", 1),
+                ("object", 1)
+            ]
+        );
+        assert_eq!(overflow["stats"]["bool"]["count"], 1);
+        assert_eq!(overflow["stats"]["null"]["count"], 1);
+        assert_eq!(overflow["stats"]["float"]["count"], 1);
+        assert_eq!(overflow["stats"]["array"]["count"], 1);
+        assert_eq!(overflow["stats"]["object"]["count"], 1);
+        assert_eq!(stat.overflow_count(), 5);
+    }
+}
@@ -0,0 +1,119 @@
+use std::{
+    io,
+    path::Path,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    thread,
+};
+
+use crate::{JsonStat, JsonStatConfig};
+
+impl JsonStat {
+    /// `from_jsonl_file` on each of `paths` across `threads` threads (default: one per
+    /// available core), merged in the order the paths are given so the result doesn't
+    /// depend on which thread finished first. Once a file fails no new ones are
+    /// started, and the error of the earliest failed file is returned.
+    pub fn from_jsonl_files_parallel<P: AsRef<Path> + Sync>(
+        paths: &[P],
+        threads: Option<usize>,
+        config: JsonStatConfig,
+    ) -> io::Result<JsonStat> {
+        let threads = threads
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+            .clamp(1, paths.len().max(1));
+        let next = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let mut results: Vec<(usize, io::Result<JsonStat>)> = thread::scope(|s| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| {
+                    s.spawn(|| {
+                        let mut ret = Vec::new();
+                        while !failed.load(Ordering::Relaxed) {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            let path = match paths.get(i) {
+                                Some(path) => path,
+                                None => break,
+                            };
+                            let stat = Self::from_jsonl_file(path, config.clone());
+                            if stat.is_err() {
+                                failed.store(true, Ordering::Relaxed);
+                            }
+                            ret.push((i, stat));
+                        }
+                        ret
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|w| w.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+                .collect()
+        });
+        results.sort_by_key(|(i, _)| *i);
+        let mut ret = Self::new_with_config(config);
+        for (_, stat) in results {
+            ret.merge(&stat?);
+        }
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, path::PathBuf};
+    fn write_files(name: &str, contents: &[&str]) -> Vec<PathBuf> {
+        let dir = std::env::temp_dir().join(format!("jsonstat-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        contents
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                let path = dir.join(format!("{}.jsonl", i));
+                fs::write(&path, c).unwrap();
+                path
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_merging_the_files_in_order() {
+        let files = write_files(
+            "parallel",
+            &[
+                "{\"a\": 1}\n{\"a\": 2}\n",
+                "{\"a\": \"x\"}\nbad\n",
+                "",
+                "{\"b\": [1, 2]}\n",
+            ],
+        );
+        let config = JsonStatConfig {
+            track_last_seen: true,
+            ..Default::default()
+        };
+        let mut expected = JsonStat::new_with_config(config.clone());
+        for path in &files {
+            expected.merge(&JsonStat::from_jsonl_file(path, config.clone()).unwrap());
+        }
+        for threads in [None, Some(1), Some(3), Some(16)] {
+            let stat =
+                JsonStat::from_jsonl_files_parallel(&files, threads, config.clone()).unwrap();
+            assert_eq!(stat.to_json_str(true), expected.to_json_str(true));
+            assert_eq!(stat.last_seen_index(), expected.last_seen_index());
+            assert_eq!((stat.record_count(), stat.failed_line_count()), (4, 1));
+        }
+        let none: &[PathBuf] = &[];
+        assert!(JsonStat::from_jsonl_files_parallel(none, None, config)
+            .unwrap()
+            .is_empty());
+        fs::remove_dir_all(files[0].parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        let mut files = write_files("parallel-missing", &["{\"a\": 1}\n"]);
+        files.push(files[0].with_file_name("missing.jsonl"));
+        let ret = JsonStat::from_jsonl_files_parallel(&files, Some(2), JsonStatConfig::default());
+        assert_eq!(ret.err().unwrap().kind(), io::ErrorKind::NotFound);
+        fs::remove_dir_all(files[0].parent().unwrap()).unwrap();
+    }
+}
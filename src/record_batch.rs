@@ -0,0 +1,138 @@
+use std::sync::Arc;
+
+use arrow_array::{
+    Array, ArrayRef, Float64Array, Int64Array, RecordBatch, StringArray, UInt64Array,
+};
+use arrow_schema::{Field, Schema};
+
+use crate::{JsonStat, JsonStatItem, MaxMinCount};
+
+fn min<T: Copy + PartialOrd + Default>(m: &MaxMinCount<T>) -> Option<T> {
+    m.extremes().map(|(min, _)| min)
+}
+
+fn max<T: Copy + PartialOrd + Default>(m: &MaxMinCount<T>) -> Option<T> {
+    m.extremes().map(|(_, max)| max)
+}
+
+fn column<A: FromIterator<Option<T>> + Array + 'static, T>(
+    items: &[&JsonStatItem],
+    f: impl Fn(&JsonStatItem) -> Option<T>,
+) -> ArrayRef {
+    Arc::new(items.iter().map(|v| f(v)).collect::<A>())
+}
+
+fn count(c: usize) -> Option<u64> {
+    Some(c as u64).filter(|c| *c > 0)
+}
+
+impl JsonStat {
+    pub fn to_record_batch(&self) -> RecordBatch {
+        let items: Vec<&JsonStatItem> = self.items.values().collect();
+        let columns: Vec<(&str, ArrayRef)> = vec![
+            (
+                "key",
+                Arc::new(StringArray::from_iter_values(self.items.keys())),
+            ),
+            (
+                "null_count",
+                column::<UInt64Array, _>(&items, |v| count(v.null.count)),
+            ),
+            (
+                "bool_count",
+                column::<UInt64Array, _>(&items, |v| count(v.bool.count)),
+            ),
+            (
+                "int_count",
+                column::<UInt64Array, _>(&items, |v| count(v.int.count)),
+            ),
+            // unsigned values past i64::MAX don't fit the column and are left null
+            (
+                "int_min",
+                column::<Int64Array, _>(&items, |v| min(&v.int).and_then(|n| n.try_into().ok())),
+            ),
+            (
+                "int_max",
+                column::<Int64Array, _>(&items, |v| max(&v.int).and_then(|n| n.try_into().ok())),
+            ),
+            (
+                "float_count",
+                column::<UInt64Array, _>(&items, |v| count(v.float_count())),
+            ),
+            (
+                "float_min",
+                column::<Float64Array, _>(&items, |v| min(&v.float)),
+            ),
+            (
+                "float_max",
+                column::<Float64Array, _>(&items, |v| max(&v.float)),
+            ),
+            (
+                "string_count",
+                column::<UInt64Array, _>(&items, |v| count(v.string.count)),
+            ),
+            (
+                "string_min",
+                column::<UInt64Array, _>(&items, |v| min(&v.string).map(|l| l as u64)),
+            ),
+            (
+                "string_max",
+                column::<UInt64Array, _>(&items, |v| max(&v.string).map(|l| l as u64)),
+            ),
+            (
+                "array_count",
+                column::<UInt64Array, _>(&items, |v| count(v.array.count)),
+            ),
+            (
+                "array_min",
+                column::<UInt64Array, _>(&items, |v| min(&v.array).map(|l| l as u64)),
+            ),
+            (
+                "array_max",
+                column::<UInt64Array, _>(&items, |v| max(&v.array).map(|l| l as u64)),
+            ),
+            (
+                "object_count",
+                column::<UInt64Array, _>(&items, |v| count(v.object.count)),
+            ),
+        ];
+        let fields: Vec<Field> = columns
+            .iter()
+            .map(|(name, c)| Field::new(*name, c.data_type().clone(), *name != "key"))
+            .collect();
+        RecordBatch::try_new(
+            Arc::new(Schema::new(fields)),
+            columns.into_iter().map(|(_, c)| c).collect(),
+        )
+        .expect("record batch columns match schema")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_row_per_key() {
+        let mut stat = JsonStat::new();
+        stat.stat_str(r#"{"a": 1, "s": "xy"}"#);
+        stat.stat_str(r#"{"a": 5, "s": "x", "f": 0.5}"#);
+        let batch = stat.to_record_batch();
+        assert_eq!(batch.num_rows(), stat.key_count());
+        let column = |name: &str| batch.column_by_name(name).unwrap().clone();
+        let keys = column("key");
+        let keys = keys.as_any().downcast_ref::<StringArray>().unwrap();
+        let row = (0..keys.len()).find(|i| keys.value(*i) == ".a").unwrap();
+        let int = |name: &str| {
+            let c = column(name);
+            let c = c.as_any().downcast_ref::<Int64Array>().unwrap();
+            c.is_valid(row).then(|| c.value(row))
+        };
+        assert_eq!((int("int_min"), int("int_max")), (Some(1), Some(5)));
+        let counts = column("int_count");
+        let counts = counts.as_any().downcast_ref::<UInt64Array>().unwrap();
+        assert_eq!(counts.value(row), 2);
+        let floats = column("float_count");
+        assert!(floats.is_null(row));
+    }
+}
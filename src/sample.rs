@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+// strings in a sample keep at most this many chars
+const SAMPLE_MAX_CHARS: usize = 64;
+// arrays/objects in a sample keep at most this many elements/members, nested at most
+// this many levels; deeper containers are emptied
+const SAMPLE_MAX_ITEMS: usize = 3;
+const SAMPLE_MAX_DEPTH: usize = 2;
+
+#[derive(Serialize, Deserialize, Clone)]
+struct SampleEntry {
+    key: u64,
+    value: Value,
+}
+
+// Bottom-k reservoir: every value is ranked by a hash of its content and the k lowest
+// ranks are kept. Each distinct value is equally likely to be picked, the choice doesn't
+// depend on the order values arrived in, and merging two reservoirs gives exactly the
+// reservoir of their union.
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct Samples {
+    k: usize,
+    // sorted by key
+    entries: Vec<SampleEntry>,
+}
+impl Samples {
+    pub(crate) fn new(k: usize) -> Self {
+        Self {
+            k,
+            entries: Vec::new(),
+        }
+    }
+    // `hash` ranks the value, `value` is what gets kept (already truncated/redacted)
+    pub(crate) fn add(&mut self, hash: u64, value: impl FnOnce() -> Value) {
+        // spread the hash out so the ranking isn't correlated with other users of it
+        let key = hash.wrapping_mul(0x9e37_79b9_7f4a_7c15).rotate_left(29);
+        let pos = match self.entries.binary_search_by_key(&key, |e| e.key) {
+            Ok(_) => return,
+            Err(pos) => pos,
+        };
+        if pos >= self.k {
+            return;
+        }
+        self.entries.insert(
+            pos,
+            SampleEntry {
+                key,
+                value: value(),
+            },
+        );
+        self.entries.truncate(self.k);
+    }
+    pub(crate) fn merge(&mut self, other: &Self) {
+        for o in other.entries.iter() {
+            if let Err(pos) = self.entries.binary_search_by_key(&o.key, |e| e.key) {
+                if pos < self.k {
+                    self.entries.insert(pos, o.clone());
+                    self.entries.truncate(self.k);
+                }
+            }
+        }
+    }
+    pub(crate) fn to_json_value(&self) -> Value {
+        self.entries.iter().map(|e| e.value.clone()).collect()
+    }
+}
+
+pub(crate) fn truncate_sample(value: &Value) -> Value {
+    truncate_at(value, 0)
+}
+
+fn truncate_at(value: &Value, depth: usize) -> Value {
+    match value {
+        Value::String(s) => match s.char_indices().nth(SAMPLE_MAX_CHARS) {
+            Some((end, _)) => Value::String(format!("{}...", &s[..end])),
+            None => value.clone(),
+        },
+        Value::Array(_) if depth >= SAMPLE_MAX_DEPTH => Value::Array(Vec::new()),
+        Value::Array(arr) => arr
+            .iter()
+            .take(SAMPLE_MAX_ITEMS)
+            .map(|v| truncate_at(v, depth + 1))
+            .collect(),
+        Value::Object(_) if depth >= SAMPLE_MAX_DEPTH => Value::Object(Map::new()),
+        Value::Object(obj) => Value::Object(
+            obj.iter()
+                .take(SAMPLE_MAX_ITEMS)
+                .map(|(k, v)| (k.clone(), truncate_at(v, depth + 1)))
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
@@ -0,0 +1,179 @@
+use serde_json::{json, Map, Value};
+
+use crate::{is_index_suffix, JsonStat, TRUNCATED_KEY_MARKER};
+
+const DRAFT_07: &str = "http://json-schema.org/draft-07/schema#";
+
+impl JsonStat {
+    /// Infers a draft-07 JSON Schema by re-nesting the flattened key paths. A member is
+    /// required when it was seen as often as its parent object. With a group key each
+    /// group's schema becomes one branch of an `anyOf`.
+    pub fn to_json_schema(&self) -> Value {
+        let roots = self.root_keys();
+        let mut schema = match roots.as_slice() {
+            [] => Map::new(),
+            [root] => self.key_schema(root),
+            _ => {
+                let mut schema = Map::new();
+                let branches: Vec<Value> = roots
+                    .iter()
+                    .map(|k| Value::Object(self.key_schema(k)))
+                    .collect();
+                schema.insert("anyOf".to_string(), json!(branches));
+                schema
+            }
+        };
+        schema.insert("$schema".to_string(), json!(DRAFT_07));
+        Value::Object(schema)
+    }
+    fn key_schema(&self, key: &str) -> Map<String, Value> {
+        let item = &self.items[key];
+        let mut schema = Map::new();
+        let mut types = Vec::new();
+        if item.null.count > 0 {
+            types.push("null");
+        }
+        if item.bool.count > 0 {
+            types.push("boolean");
+        }
+        if item.float_count() > 0 {
+            types.push("number");
+        } else if item.int.count > 0 {
+            types.push("integer");
+        }
+        if item.string.count > 0 {
+            types.push("string");
+        }
+        if item.array.count > 0 {
+            types.push("array");
+        }
+        if item.object.count > 0 {
+            types.push("object");
+        }
+        match types.as_slice() {
+            [] => {}
+            [t] => {
+                schema.insert("type".to_string(), json!(t));
+            }
+            _ => {
+                schema.insert("type".to_string(), json!(types));
+            }
+        }
+        if let Some((min, max)) = item.number_range() {
+            schema.insert("minimum".to_string(), min);
+            schema.insert("maximum".to_string(), max);
+        }
+        if let Some((min, max)) = item.string.extremes() {
+            schema.insert("minLength".to_string(), json!(min));
+            schema.insert("maxLength".to_string(), json!(max));
+        }
+        // a `max_key_length` cut folds everything below into this key, and the child
+        // paths built from it fold right back into it, so there's nothing to descend to
+        let folded = key.ends_with(TRUNCATED_KEY_MARKER);
+        if item.array.count > 0 {
+            if let Some((min, max)) = item.array.extremes() {
+                schema.insert("minItems".to_string(), json!(min));
+                schema.insert("maxItems".to_string(), json!(max));
+            }
+            let mut positions = Vec::new();
+            loop {
+                let k = self.config.indexed_element_key(key, positions.len());
+                // cut positions all share one key, so they can't be told apart
+                if folded || !self.items.contains_key(&k) || k.ends_with(TRUNCATED_KEY_MARKER) {
+                    break;
+                }
+                positions.push(Value::Object(self.key_schema(&k)));
+            }
+            let element_key = self.config.element_key(key);
+            let elements = (!folded && self.items.contains_key(&element_key))
+                .then(|| Value::Object(self.key_schema(&element_key)));
+            if positions.is_empty() {
+                if let Some(elements) = elements {
+                    schema.insert("items".to_string(), elements);
+                }
+            } else {
+                schema.insert("items".to_string(), json!(positions));
+                if let Some(elements) = elements {
+                    schema.insert("additionalItems".to_string(), elements);
+                }
+            }
+        }
+        if item.object.count > 0 && !folded {
+            let prefix = format!("{}{}", key, self.config.separator());
+            let mut properties = Map::new();
+            let mut required = Vec::new();
+            for (k, v) in self.items.range(prefix.clone()..) {
+                if !k.starts_with(&prefix) {
+                    break;
+                }
+                if let Some(name) = self.member_name(&k[prefix.len()..]) {
+                    if v.total_count() >= item.object.count {
+                        required.push(name.clone());
+                    }
+                    properties.insert(name, Value::Object(self.key_schema(k)));
+                }
+            }
+            if !properties.is_empty() {
+                schema.insert("properties".to_string(), Value::Object(properties));
+            }
+            if !required.is_empty() {
+                schema.insert("required".to_string(), json!(required));
+            }
+        }
+        schema
+    }
+    // the unescaped member name when `rest` is a single path segment
+    fn member_name(&self, mut rest: &str) -> Option<String> {
+        let separator = self.config.separator();
+        let array_marker = self.config.array_marker();
+        let mut name = String::new();
+        while let Some(c) = rest.chars().next() {
+            if c == '\\' && self.config.escape_keys {
+                rest = &rest[1..];
+                if let Some(c) = rest.chars().next() {
+                    name.push(c);
+                    rest = &rest[c.len_utf8()..];
+                }
+                continue;
+            }
+            if rest.starts_with(separator)
+                || (!array_marker.is_empty() && rest.starts_with(array_marker))
+                || (self.config.array_index_limit.is_some() && is_index_suffix(rest))
+            {
+                return None;
+            }
+            name.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+        Some(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::JsonStatConfig;
+    #[test]
+    fn truncated_nested_arrays_stop_at_the_cut() {
+        for (max, array_index_limit) in [(8, None), (8, Some(4)), (12, Some(4)), (14, None)] {
+            let mut stat = JsonStat::new_with_config(JsonStatConfig {
+                max_key_length: Some(max),
+                array_index_limit,
+                ..Default::default()
+            });
+            stat.stat_str(r#"{"abcdefghijk": [[1], [2, [3]]], "a": {"bcdefghijklm": [{"x": 1}]}}"#);
+            let schema = stat.to_json_schema();
+            let rendered = schema.to_string();
+            assert!(rendered.contains("\"type\":\"object\""), "{}", rendered);
+        }
+
+        let mut stat = JsonStat::new_with_config(JsonStatConfig {
+            max_key_length: Some(8),
+            ..Default::default()
+        });
+        stat.stat_str(r#"{"abcdefghijk": [[1]]}"#);
+        let member = &stat.to_json_schema()["properties"]["abcdefg<truncated>"];
+        assert_eq!(member["type"], json!(["integer", "array"]));
+        assert!(member.get("items").is_none());
+    }
+}
@@ -0,0 +1,205 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex, MutexGuard, TryLockError,
+};
+
+use serde_json::Value;
+
+use crate::{hash, JsonStat};
+
+/// A `JsonStat` that many threads can feed through `&self`, e.g. behind an `Arc`. Records
+/// go to one of several independently locked shards; `snapshot`/`into_inner` merge the
+/// shards back together. With a group key, the shard is picked by hashing the record's
+/// group, so each group's keys live in one shard and threads only wait on each other
+/// for records of groups sharing a shard. Ungrouped records all share one root key, so
+/// hashing would send them to one shard; they take the next shard that isn't locked.
+/// Record order is lost across shards, so order dependent tracking (`track_last_seen`,
+/// `track_type_changes`) reads as if the shards were stat'ed one after another.
+pub struct SharedJsonStat {
+    shards: Vec<Mutex<JsonStat>>,
+    next: AtomicUsize,
+    // an empty stat with the shards' config and group key, to work out group keys with
+    router: JsonStat,
+}
+impl SharedJsonStat {
+    /// One shard per available core. `stat` is the first shard, so whatever it already
+    /// holds is kept (once); the others start empty with its config and group key.
+    pub fn new(stat: JsonStat) -> Self {
+        let shards = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self::with_shards(stat, shards)
+    }
+    pub fn with_shards(stat: JsonStat, shards: usize) -> Self {
+        let empty = JsonStat {
+            group_keys: stat.group_keys.clone(),
+            ..JsonStat::new_with_config(stat.config.clone())
+        };
+        let mut ret = vec![Mutex::new(stat)];
+        ret.extend((1..shards.max(1)).map(|_| Mutex::new(empty.clone())));
+        Self {
+            shards: ret,
+            next: AtomicUsize::new(0),
+            router: empty,
+        }
+    }
+    // Starts at the next shard in turn and takes the first one that isn't locked,
+    // waiting on the starting shard only if all of them are.
+    fn shard(&self) -> MutexGuard<'_, JsonStat> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        for i in 0..self.shards.len() {
+            match self.shards[(start + i) % self.shards.len()].try_lock() {
+                Ok(guard) => return guard,
+                Err(TryLockError::Poisoned(e)) => return e.into_inner(),
+                Err(TryLockError::WouldBlock) => {}
+            }
+        }
+        self.shards[start % self.shards.len()]
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+    // The shard owning `value`'s group, or any free shard when ungrouped.
+    fn group_shard(&self, value: &Value) -> MutexGuard<'_, JsonStat> {
+        if self.router.group_keys.is_empty() {
+            return self.shard();
+        }
+        let group = self.router.get_group_key(value);
+        let i = (hash::hash_str(&group) % self.shards.len() as u64) as usize;
+        self.shards[i].lock().unwrap_or_else(|e| e.into_inner())
+    }
+    /// Grouped lines are parsed once more to find their group; lines that don't parse
+    /// go to any free shard, which counts them as failed.
+    pub fn stat_str(&self, line: &str) -> bool {
+        if self.router.group_keys.is_empty() {
+            return self.shard().stat_str(line);
+        }
+        match serde_json::from_str(line) {
+            Ok(value) => self.group_shard(&value).stat_str(line),
+            Err(_) => self.shard().stat_str(line),
+        }
+    }
+    pub fn stat_value(&self, value: &Value) -> bool {
+        self.group_shard(value).stat_value(value)
+    }
+    /// The shards merged into one `JsonStat`, leaving them in place.
+    pub fn snapshot(&self) -> JsonStat {
+        let mut shards = self
+            .shards
+            .iter()
+            .map(|s| s.lock().unwrap_or_else(|e| e.into_inner()));
+        let mut ret = shards.next().map(|s| s.clone()).unwrap_or_default();
+        for s in shards {
+            ret.merge(&s);
+        }
+        ret
+    }
+    pub fn into_inner(self) -> JsonStat {
+        let mut shards = self
+            .shards
+            .into_iter()
+            .map(|s| s.into_inner().unwrap_or_else(|e| e.into_inner()));
+        let mut ret = shards.next().unwrap_or_default();
+        for s in shards {
+            ret.merge(&s);
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    fn record(thread: usize, i: usize) -> Value {
+        json!({ "thread": thread, "i": i, "s": format!("v{}", i % 13), "f": i as f64 / 4.0 })
+    }
+
+    #[test]
+    fn threads_feeding_one_instance_match_a_single_pass() {
+        let (threads, per_thread) = (8, 2000);
+        let shared = SharedJsonStat::with_shards(JsonStat::new(), 3);
+        std::thread::scope(|s| {
+            for t in 0..threads {
+                let shared = &shared;
+                s.spawn(move || {
+                    for i in 0..per_thread {
+                        if i % 2 == 0 {
+                            assert!(shared.stat_value(&record(t, i)));
+                        } else {
+                            assert!(shared.stat_str(&record(t, i).to_string()));
+                        }
+                    }
+                    // snapshots taken mid-stream don't disturb the writers
+                    shared.snapshot();
+                });
+            }
+        });
+        let mut single = JsonStat::new();
+        for t in 0..threads {
+            for i in 0..per_thread {
+                single.stat_value(&record(t, i));
+            }
+        }
+        let shared = shared.into_inner();
+        assert_eq!(shared.record_count(), threads * per_thread);
+        // moments merged in another order only agree up to rounding
+        let (mut shared, mut single): (Value, Value) = (
+            serde_json::from_str(&shared.to_json_str(false)).unwrap(),
+            serde_json::from_str(&single.to_json_str(false)).unwrap(),
+        );
+        for key in [".f", ".i", ".thread"] {
+            let block = if key == ".f" { "float" } else { "int" };
+            let (a, b) = (&mut shared[key][block], &mut single[key][block]);
+            for moment in ["variance", "stddev"] {
+                let (x, y) = (a[moment].as_f64().unwrap(), b[moment].as_f64().unwrap());
+                assert!((x - y).abs() <= y * 1e-12, "{} {} {}", key, x, y);
+                a[moment].take();
+                b[moment].take();
+            }
+        }
+        assert_eq!(shared, single);
+    }
+
+    #[test]
+    fn seed_data_is_counted_once() {
+        let mut seed = JsonStat::new();
+        seed.stat_value(&json!({"a": 1}));
+        let shared = SharedJsonStat::with_shards(seed, 4);
+        shared.stat_value(&json!({"a": 2}));
+        let stat = shared.snapshot();
+        assert_eq!(stat.record_count(), 2);
+        assert_eq!(stat.get(".a").unwrap().int_count(), 2);
+        assert_eq!(shared.into_inner().record_count(), 2);
+    }
+
+    #[test]
+    fn groups_stay_in_one_shard() {
+        let shared = SharedJsonStat::with_shards(JsonStat::new_by_groups(&["g"]), 4);
+        std::thread::scope(|s| {
+            for t in 0..4 {
+                let shared = &shared;
+                s.spawn(move || {
+                    for i in 0..200 {
+                        let record = json!({ "g": format!("g{}", (t + i) % 6), "i": i });
+                        if i % 2 == 0 {
+                            assert!(shared.stat_value(&record));
+                        } else {
+                            assert!(shared.stat_str(&record.to_string()));
+                        }
+                    }
+                });
+            }
+        });
+        assert!(!shared.stat_str("{"));
+        for g in 0..6 {
+            let root = format!("g{}", g);
+            let holders = shared
+                .shards
+                .iter()
+                .filter(|s| s.lock().unwrap().get(&root).is_some())
+                .count();
+            assert_eq!(holders, 1, "{}", root);
+        }
+        let stat = shared.into_inner();
+        assert_eq!(stat.record_count(), 800);
+        assert_eq!(stat.failed_line_count(), 1);
+    }
+}
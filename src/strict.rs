@@ -0,0 +1,98 @@
+use std::{collections::HashSet, fmt};
+
+use serde::{
+    de::{MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+
+// Walks a JSON document without building it, remembering whether any object repeated a
+// key. serde_json's `Value` silently keeps the last duplicate, so this needs its own pass.
+pub(crate) struct HasDuplicateKeys(pub(crate) bool);
+
+impl<'de> Deserialize<'de> for HasDuplicateKeys {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(DuplicateKeyVisitor)
+    }
+}
+
+struct DuplicateKeyVisitor;
+
+impl<'de> Visitor<'de> for DuplicateKeyVisitor {
+    type Value = HasDuplicateKeys;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any JSON value")
+    }
+    fn visit_bool<E>(self, _: bool) -> Result<Self::Value, E> {
+        Ok(HasDuplicateKeys(false))
+    }
+    fn visit_i64<E>(self, _: i64) -> Result<Self::Value, E> {
+        Ok(HasDuplicateKeys(false))
+    }
+    fn visit_u64<E>(self, _: u64) -> Result<Self::Value, E> {
+        Ok(HasDuplicateKeys(false))
+    }
+    fn visit_f64<E>(self, _: f64) -> Result<Self::Value, E> {
+        Ok(HasDuplicateKeys(false))
+    }
+    fn visit_str<E>(self, _: &str) -> Result<Self::Value, E> {
+        Ok(HasDuplicateKeys(false))
+    }
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(HasDuplicateKeys(false))
+    }
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut ret = false;
+        while let Some(HasDuplicateKeys(dup)) = seq.next_element()? {
+            ret |= dup;
+        }
+        Ok(HasDuplicateKeys(ret))
+    }
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut ret = false;
+        let mut keys = HashSet::new();
+        while let Some(key) = map.next_key::<String>()? {
+            ret |= !keys.insert(key);
+            let HasDuplicateKeys(dup) = map.next_value()?;
+            ret |= dup;
+        }
+        Ok(HasDuplicateKeys(ret))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{JsonStat, JsonStatConfig};
+    fn has_duplicates(line: &str) -> bool {
+        serde_json::from_str::<HasDuplicateKeys>(line).unwrap().0
+    }
+
+    #[test]
+    fn finds_duplicate_keys_at_any_depth() {
+        assert!(has_duplicates(r#"{"a": 1, "a": 2}"#));
+        assert!(has_duplicates(r#"[1, {"b": {"c": 1, "c": 1}}]"#));
+        assert!(!has_duplicates(
+            r#"{"a": {"a": 1}, "b": [{"a": 1}, {"a": 2}]}"#
+        ));
+    }
+
+    #[test]
+    fn strict_mode_records_duplicate_key_violations() {
+        let line = r#"{"id": 1, "id": 2}"#;
+        let mut strict = JsonStat::new_with_config(JsonStatConfig {
+            strict: true,
+            ..Default::default()
+        });
+        strict.stat_str(line);
+        strict.stat_str(r#"{"id": 3}"#);
+        strict.stat_str("NaN");
+        assert_eq!(strict.strict_violations(), 1);
+        assert_eq!(strict.record_count(), 2);
+        assert_eq!(strict.failed_line_count(), 1);
+
+        let mut lenient = JsonStat::new();
+        lenient.stat_str(line);
+        assert_eq!(lenient.strict_violations(), 0);
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{JsonStat, JsonStatItem};
+
+/// Plain-data view of one key's stats, decoupled from how `JsonStatItem` stores them.
+/// Each type's block is `None` when the key never held that type.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct KeySummary {
+    pub count: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub null: Option<CountSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bool: Option<BoolSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub int: Option<IntSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub float: Option<FloatSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub string: Option<LengthSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub array: Option<LengthSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object: Option<LengthSummary>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CountSummary {
+    pub count: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BoolSummary {
+    pub count: usize,
+    pub true_count: usize,
+    pub false_count: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct IntSummary {
+    pub count: usize,
+    pub min: i128,
+    pub max: i128,
+    pub sum: i128,
+    pub mean: f64,
+    pub stddev: f64,
+}
+
+/// min/max/sum/mean/stddev are over the finite values; `count` includes the nonfinite ones
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FloatSummary {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub sum: f64,
+    pub mean: f64,
+    pub stddev: f64,
+    pub nonfinite: usize,
+}
+
+/// lengths in bytes for strings, elements for arrays and members for objects
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LengthSummary {
+    pub count: usize,
+    pub min: usize,
+    pub max: usize,
+    pub empty: usize,
+}
+
+impl From<&JsonStatItem> for KeySummary {
+    fn from(item: &JsonStatItem) -> Self {
+        KeySummary {
+            count: item.total_count(),
+            null: (item.null.count > 0).then_some(CountSummary {
+                count: item.null.count,
+            }),
+            bool: (item.bool.count > 0).then(|| BoolSummary {
+                count: item.bool.count,
+                true_count: item.bool_true_count(),
+                false_count: item.bool_false_count(),
+            }),
+            int: (item.int.count > 0).then(|| IntSummary {
+                count: item.int.count,
+                min: item.int.min,
+                max: item.int.max,
+                sum: item.int_sum,
+                mean: item.int_mean(),
+                stddev: item.int_moments.stddev(),
+            }),
+            float: (item.float_count() > 0).then(|| FloatSummary {
+                count: item.float_count(),
+                min: item.float.min,
+                max: item.float.max,
+                sum: item.float_sum,
+                mean: if item.float.count > 0 {
+                    item.float_mean()
+                } else {
+                    0.0
+                },
+                stddev: item.float_moments.stddev(),
+                nonfinite: item.float_nonfinite,
+            }),
+            string: (item.string.count > 0).then_some(LengthSummary {
+                count: item.string.count,
+                min: item.string.min,
+                max: item.string.max,
+                empty: item.string_empty,
+            }),
+            array: (item.array.count > 0).then_some(LengthSummary {
+                count: item.array.count,
+                min: item.array.min,
+                max: item.array.max,
+                empty: item.array_empty,
+            }),
+            object: (item.object.count > 0).then_some(LengthSummary {
+                count: item.object.count,
+                min: item.object_keys.min,
+                max: item.object_keys.max,
+                empty: item.object_empty,
+            }),
+        }
+    }
+}
+
+impl JsonStat {
+    /// every output key's stats as `KeySummary`s
+    pub fn summaries(&self) -> BTreeMap<String, KeySummary> {
+        self.output_items()
+            .map(|(k, v)| (k.clone(), KeySummary::from(v)))
+            .collect()
+    }
+}
@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+// Merging t-digest: values are kept as weighted centroids, sorted by mean, with the
+// weight a centroid may reach shrinking toward both tails so extreme quantiles stay
+// accurate. Roughly compression * pi / 2 centroids survive each compression.
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct TDigest {
+    compression: f64,
+    // (mean, weight)
+    centroids: Vec<(f64, f64)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    buffer: Vec<(f64, f64)>,
+    min: f64,
+    max: f64,
+}
+impl TDigest {
+    pub(crate) fn new(compression: usize) -> Self {
+        Self {
+            compression: compression.max(1) as f64,
+            centroids: Vec::new(),
+            buffer: Vec::new(),
+            min: 0.0,
+            max: 0.0,
+        }
+    }
+    fn is_empty(&self) -> bool {
+        self.centroids.is_empty() && self.buffer.is_empty()
+    }
+    fn push(&mut self, mean: f64, weight: f64, min: f64, max: f64) {
+        if self.is_empty() {
+            self.min = min;
+            self.max = max;
+        } else {
+            self.min = self.min.min(min);
+            self.max = self.max.max(max);
+        }
+        self.buffer.push((mean, weight));
+        if self.buffer.len() as f64 > self.compression * 5.0 {
+            self.compress();
+        }
+    }
+    pub(crate) fn add(&mut self, x: f64) {
+        self.push(x, 1.0, x, x);
+    }
+    // every value counted `weight` times over
+    pub(crate) fn scale(&mut self, weight: usize) {
+        for c in self.centroids.iter_mut().chain(self.buffer.iter_mut()) {
+            c.1 *= weight as f64;
+        }
+    }
+    pub(crate) fn merge(&mut self, other: &Self) {
+        if other.is_empty() {
+            return;
+        }
+        let (min, max) = (other.min, other.max);
+        for (mean, weight) in other.centroids.iter().chain(other.buffer.iter()) {
+            self.push(*mean, *weight, min, max);
+        }
+    }
+    fn compress(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let mut all = std::mem::take(&mut self.centroids);
+        all.append(&mut self.buffer);
+        all.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let total: f64 = all.iter().map(|c| c.1).sum();
+        let mut merged = Vec::new();
+        let mut current = all[0];
+        let mut before = 0.0;
+        for c in all.into_iter().skip(1) {
+            let weight = current.1 + c.1;
+            let q = (before + weight / 2.0) / total;
+            if weight <= 4.0 * total * q * (1.0 - q) / self.compression {
+                current.0 += (c.0 - current.0) * c.1 / weight;
+                current.1 = weight;
+            } else {
+                before += current.1;
+                merged.push(current);
+                current = c;
+            }
+        }
+        merged.push(current);
+        self.centroids = merged;
+    }
+    pub(crate) fn quantile(&self, q: f64) -> Option<f64> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut digest = self.clone();
+        digest.compress();
+        let centroids = &digest.centroids;
+        let total: f64 = centroids.iter().map(|c| c.1).sum();
+        let rank = q.clamp(0.0, 1.0) * total;
+        // interpolate between centroid centres, and toward min/max past the outer ones
+        let mut prev = (self.min, 0.0);
+        let mut before = 0.0;
+        for (mean, weight) in centroids.iter() {
+            let center = before + weight / 2.0;
+            if rank < center {
+                let span = center - prev.1;
+                let t = if span > 0.0 {
+                    (rank - prev.1) / span
+                } else {
+                    0.0
+                };
+                return Some(prev.0 + (mean - prev.0) * t);
+            }
+            prev = (*mean, center);
+            before += weight;
+        }
+        let span = total - prev.1;
+        let t = if span > 0.0 {
+            (rank - prev.1) / span
+        } else {
+            0.0
+        };
+        Some(prev.0 + (self.max - prev.0) * t)
+    }
+    pub(crate) fn to_json_value(&self) -> Value {
+        json!({
+            "p50": self.quantile(0.5),
+            "p95": self.quantile(0.95),
+            "p99": self.quantile(0.99),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 0..n shuffled with a fixed LCG, so insertion order isn't sorted
+    fn shuffled(n: u64) -> Vec<f64> {
+        let mut values: Vec<f64> = (0..n).map(|i| i as f64).collect();
+        let mut state = 42u64;
+        for i in (1..values.len()).rev() {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            values.swap(i, (state >> 33) as usize % (i + 1));
+        }
+        values
+    }
+
+    #[test]
+    fn estimates_are_close_to_exact_quantiles() {
+        let n = 10_000;
+        let mut digest = TDigest::new(100);
+        for x in shuffled(n) {
+            digest.add(x);
+        }
+        for q in [0.01, 0.25, 0.5, 0.95, 0.99] {
+            let exact = q * (n - 1) as f64;
+            let estimate = digest.quantile(q).unwrap();
+            assert!(
+                (estimate - exact).abs() < n as f64 * 0.01,
+                "{} {}",
+                q,
+                estimate
+            );
+        }
+        assert_eq!(digest.quantile(0.0), Some(0.0));
+        assert_eq!(digest.quantile(1.0), Some((n - 1) as f64));
+        assert_eq!(TDigest::new(100).quantile(0.5), None);
+    }
+
+    #[test]
+    fn merged_digests_stay_close() {
+        let values = shuffled(10_000);
+        let (mut a, mut b) = (TDigest::new(100), TDigest::new(100));
+        for (i, x) in values.iter().enumerate() {
+            if i % 3 == 0 {
+                a.add(*x)
+            } else {
+                b.add(*x)
+            }
+        }
+        a.merge(&b);
+        for q in [0.5, 0.95, 0.99] {
+            let exact = q * 9_999.0;
+            assert!((a.quantile(q).unwrap() - exact).abs() < 100.0);
+        }
+    }
+}
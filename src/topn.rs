@@ -0,0 +1,64 @@
+use std::cmp::Reverse;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+#[derive(Serialize, Deserialize, Clone)]
+struct TopEntry {
+    value: Value,
+    count: usize,
+}
+
+// Space-saving frequency sketch. It keeps a few times more counters than it reports,
+// so values near the reporting cut-off aren't evicted by noise; once full, an unseen
+// value takes over the smallest counter.
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct TopN {
+    n: usize,
+    entries: Vec<TopEntry>,
+}
+impl TopN {
+    pub(crate) fn new(n: usize) -> Self {
+        Self {
+            n,
+            entries: Vec::new(),
+        }
+    }
+    fn capacity(&self) -> usize {
+        self.n.max(1) * 4
+    }
+    pub(crate) fn add(&mut self, value: Value) {
+        if let Some(e) = self.entries.iter_mut().find(|e| e.value == value) {
+            e.count += 1;
+        } else if self.entries.len() < self.capacity() {
+            self.entries.push(TopEntry { value, count: 1 });
+        } else if let Some(e) = self.entries.iter_mut().min_by_key(|e| e.count) {
+            e.value = value;
+            e.count += 1;
+        }
+    }
+    // every value counted `weight` times over
+    pub(crate) fn scale(&mut self, weight: usize) {
+        self.entries.iter_mut().for_each(|e| e.count *= weight);
+    }
+    pub(crate) fn merge(&mut self, other: &Self) {
+        for o in other.entries.iter() {
+            if let Some(e) = self.entries.iter_mut().find(|e| e.value == o.value) {
+                e.count += o.count;
+            } else {
+                self.entries.push(o.clone());
+            }
+        }
+        self.entries.sort_by_key(|e| Reverse(e.count));
+        self.entries.truncate(self.capacity());
+    }
+    pub(crate) fn to_json_value(&self) -> Value {
+        let mut entries: Vec<&TopEntry> = self.entries.iter().collect();
+        entries.sort_by_key(|e| Reverse(e.count));
+        entries
+            .into_iter()
+            .take(self.n)
+            .map(|e| json!({"value": e.value, "count": e.count}))
+            .collect()
+    }
+}